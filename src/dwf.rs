@@ -11,3 +11,7 @@ pub fn to_c_bool(v: bool) -> BOOL {
         false_ as BOOL
     }
 }
+
+pub fn is_bit_set(fs: ::std::os::raw::c_int, bit: ::std::os::raw::c_int) -> bool {
+    fs & (1 << bit) != 0
+}
@@ -7,6 +7,8 @@ use std::ffi::CStr;
 use std::mem;
 use std::os::raw::c_char;
 use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::sync::mpsc::Receiver;
 
 use time::Duration;
 
@@ -76,6 +78,23 @@ impl Display for Error {
     }
 }
 
+impl Error {
+    fn new(kind: ErrorKind, message: &str) -> Error {
+        Error {
+            kind,
+            message: message.to_owned(),
+        }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
 fn get_last_error_code() -> ErrorKind {
     unsafe {
         let mut error_code: DWFERC = mem::uninitialized();
@@ -141,14 +160,83 @@ impl TriggerSource {
         self as TRIGSRC
     }
 
-    fn from_code(code: TRIGSRC) -> TriggerSource {
-        unsafe { mem::transmute(code as isize) }
+    fn from_code(code: TRIGSRC) -> Result<TriggerSource> {
+        match code {
+            trigsrcNone => Ok(TriggerSource::NoTrigger),
+            trigsrcPC => Ok(TriggerSource::PC),
+            trigsrcDetectorAnalogIn => Ok(TriggerSource::DetectorAnalogIn),
+            trigsrcDetectorDigitalIn => Ok(TriggerSource::DetectorDigitalIn),
+            trigsrcAnalogIn => Ok(TriggerSource::AnalogIn),
+            trigsrcDigitalIn => Ok(TriggerSource::DigitalIn),
+            trigsrcDigitalOut => Ok(TriggerSource::DigitalOut),
+            trigsrcAnalogOut1 => Ok(TriggerSource::AnalogOut1),
+            trigsrcAnalogOut2 => Ok(TriggerSource::AnalogOut2),
+            trigsrcAnalogOut3 => Ok(TriggerSource::AnalogOut3),
+            trigsrcAnalogOut4 => Ok(TriggerSource::AnalogOut4),
+            trigsrcExternal1 => Ok(TriggerSource::External1),
+            trigsrcExternal2 => Ok(TriggerSource::External2),
+            trigsrcExternal3 => Ok(TriggerSource::External3),
+            trigsrcExternal4 => Ok(TriggerSource::External4),
+            trigsrcHigh => Ok(TriggerSource::High),
+            trigsrcLow => Ok(TriggerSource::Low),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown trigger source #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum TriggerSlope {
+    Rise = DwfTriggerSlopeRise as isize,
+    Fall = DwfTriggerSlopeFall as isize,
+    Either = DwfTriggerSlopeEither as isize,
+}
+
+impl TriggerSlope {
+    fn code(self) -> DwfTriggerSlope {
+        self as DwfTriggerSlope
+    }
+
+    fn from_code(code: DwfTriggerSlope) -> Result<TriggerSlope> {
+        match code {
+            DwfTriggerSlopeRise => Ok(TriggerSlope::Rise),
+            DwfTriggerSlopeFall => Ok(TriggerSlope::Fall),
+            DwfTriggerSlopeEither => Ok(TriggerSlope::Either),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown trigger slope #{}", code))),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum GeneratorStatus {
+    Ready = DwfStateReady as isize,
+    Config = DwfStateConfig as isize,
+    Prefill = DwfStatePrefill as isize,
+    Armed = DwfStateArmed as isize,
+    Waiting = DwfStateWait as isize,
+    Running = DwfStateRunning as isize,
+    Done = DwfStateDone as isize,
+}
+
+impl GeneratorStatus {
+    fn from_code(code: DwfState) -> Result<GeneratorStatus> {
+        match code {
+            DwfStateReady => Ok(GeneratorStatus::Ready),
+            DwfStateConfig => Ok(GeneratorStatus::Config),
+            DwfStatePrefill => Ok(GeneratorStatus::Prefill),
+            DwfStateArmed => Ok(GeneratorStatus::Armed),
+            DwfStateWait => Ok(GeneratorStatus::Waiting),
+            DwfStateRunning => Ok(GeneratorStatus::Running),
+            DwfStateDone => Ok(GeneratorStatus::Done),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown generator state #{}", code))),
+        }
     }
 }
 
 #[derive(PartialEq, Debug)]
 pub struct DeviceConfigInfo {
     device_ix: c_int,
+    device_id: DEVID,
     config_ix: c_int,
     pub analog_inputs: i32,
     pub analog_outputs: i32,
@@ -170,11 +258,20 @@ fn handle_dwf_errors(res: BOOL) -> Result<()> {
     }
 }
 
+fn to_seconds(duration: Duration) -> f64 {
+    duration.num_nanoseconds().unwrap() as f64 / 1e9
+}
+
+fn from_seconds(secs: f64) -> Duration {
+    Duration::nanoseconds((secs * 1e9).round() as i64)
+}
+
 impl DeviceConfigInfo {
     pub fn open(&self) -> Result<Device> {
         unsafe {
             let mut dev = Device {
                 handle: mem::uninitialized(),
+                id: self.device_id,
             };
             handle_dwf_errors(FDwfDeviceConfigOpen(self.device_ix, self.config_ix, (&mut dev.handle) as *mut HDWF))?;
             Ok(dev)
@@ -261,6 +358,7 @@ pub fn devices() -> Result<DeviceInfoList> {
 
                 configs.insert(config_ix as usize, DeviceConfigInfo {
                     device_ix,
+                    device_id: id,
                     config_ix,
                     analog_inputs,
                     analog_outputs,
@@ -291,64 +389,164 @@ pub fn devices() -> Result<DeviceInfoList> {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum AnalogOutNodeKind {
+    Carrier = AnalogOutNodeCarrier as isize,
+    /// Frequency modulation
+    FM = AnalogOutNodeFM as isize,
+    /// Amplitude modulation
+    AM = AnalogOutNodeAM as isize,
+}
+
+impl AnalogOutNodeKind {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+}
+
 pub struct AnalogOutNode<'a> {
     out: &'a AnalogOut<'a>,
     ix: c_int,
 }
 
 impl<'a> AnalogOutNode<'a> {
-    pub fn set_function(&self, func: AnalogOutFunction) -> Result<()> {
+    fn check_function(&self, func: FUNC) -> Result<()> {
         unsafe {
-            match func {
-                AnalogOutFunction::Const { offset } => {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfAnalogOutNodeFunctionInfo(self.out.device.handle, self.out.ix, self.ix, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, func as c_int) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("node #{} of channel #{} doesn't support function #{}",
+                                                                        self.ix, self.out.ix, func)));
+            }
+        }
+        Ok(())
+    }
+
+    fn set_periodic(&self, func: FUNC, frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64) -> Result<()> {
+        self.check_function(func)?;
+        unsafe {
+            handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, func))?;
+            handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
+            handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
+            handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
+            handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
+            handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
+        }
+        Ok(())
+    }
+
+    /// Custom samples outside the -1..1 range are rejected, see `set_custom`
+    pub fn set_function(&self, func: AnalogOutFunction) -> Result<()> {
+        match func {
+            AnalogOutFunction::Const { offset } => {
+                self.check_function(funcDC)?;
+                unsafe {
                     handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcDC))?;
                     handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                },
-                AnalogOutFunction::RampUp { frequency, amplitude, offset, symmetry, phase_deg } => {
-                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcRampUp))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
-                    handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
-                },
-                AnalogOutFunction::RampDown { frequency, amplitude, offset, symmetry, phase_deg } => {
-                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcRampDown))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
-                    handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
-                },
-                AnalogOutFunction::Sine { frequency, amplitude, offset, symmetry, phase_deg } => {
-                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcSine))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
-                    handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
-                },
-                AnalogOutFunction::Square { frequency, amplitude, offset, symmetry, phase_deg } => {
-                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcSquare))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
-                    handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
-                },
-                AnalogOutFunction::Triangle { frequency, amplitude, offset, symmetry, phase_deg } => {
-                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcTriangle))?;
+                }
+            },
+            AnalogOutFunction::RampUp { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcRampUp, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::RampDown { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcRampDown, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Sine { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcSine, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Square { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcSquare, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Triangle { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcTriangle, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Pulse { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcPulse, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Trapezium { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcTrapezium, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::SinePower { frequency, amplitude, offset, symmetry, phase_deg } =>
+                self.set_periodic(funcSinePower, frequency, amplitude, offset, symmetry, phase_deg)?,
+            AnalogOutFunction::Noise { frequency, amplitude, offset } => {
+                self.check_function(funcNoise)?;
+                unsafe {
+                    handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcNoise))?;
                     handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
                     handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
                     handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
-                    handle_dwf_errors(FDwfAnalogOutNodeSymmetrySet(self.out.device.handle, self.out.ix, self.ix, symmetry))?;
-                    handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
-                },
+                }
+            },
+            AnalogOutFunction::Custom { mut samples, frequency, amplitude, offset, phase_deg } => {
+                if let Some(sample) = samples.iter().find(|sample| sample.abs() > 1.0) {
+                    return Err(Error::new(ErrorKind::InvalidParameter4,
+                                          &format!("custom sample {} is out of the -1..1 range, use set_custom to clip", sample)));
+                }
+                self.upload_custom(&mut samples, frequency, amplitude, offset, phase_deg)?;
+            },
+        }
+        Ok(())
+    }
+
+    /// One period of an arbitrary waveform, like `AnalogOutFunction::Custom`, but `samples` outside the -1..1 range
+    /// are clipped instead of rejected. Returns the number of clipped samples.
+    pub fn set_custom(&self, mut samples: Vec<f64>, frequency: f64, amplitude: f64, offset: f64, phase_deg: f64) -> Result<usize> {
+        let mut clipped = 0;
+        for sample in samples.iter_mut().filter(|sample| sample.is_finite() && sample.abs() > 1.0) {
+            *sample = sample.max(-1.0).min(1.0);
+            clipped += 1;
+        }
+        self.upload_custom(&mut samples, frequency, amplitude, offset, phase_deg)?;
+        Ok(clipped)
+    }
+
+    fn upload_custom(&self, samples: &mut [f64], frequency: f64, amplitude: f64, offset: f64, phase_deg: f64) -> Result<()> {
+        self.check_function(funcCustom)?;
+        if samples.iter().any(|sample| !sample.is_finite()) {
+            return Err(Error::new(ErrorKind::InvalidParameter4, "custom samples must be finite"));
+        }
+        unsafe {
+            let (mut min, mut max): (c_int, c_int) = (0, 0);
+            handle_dwf_errors(FDwfAnalogOutNodeDataInfo(self.out.device.handle, self.out.ix, self.ix,
+                                                        &mut min as *mut c_int, &mut max as *mut c_int))?;
+            if samples.len() < min as usize || samples.len() > max as usize {
+                return Err(Error::new(ErrorKind::InvalidParameter4,
+                                      &format!("{} custom samples given, [{}, {}] expected", samples.len(), min, max)));
             }
+            handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.out.device.handle, self.out.ix, self.ix, funcCustom))?;
+            handle_dwf_errors(FDwfAnalogOutNodeDataSet(self.out.device.handle, self.out.ix, self.ix,
+                                                       samples.as_mut_ptr(), samples.len() as c_int))?;
+            handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.out.device.handle, self.out.ix, self.ix, frequency))?;
+            handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.out.device.handle, self.out.ix, self.ix, amplitude))?;
+            handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.out.device.handle, self.out.ix, self.ix, offset))?;
+            handle_dwf_errors(FDwfAnalogOutNodePhaseSet(self.out.device.handle, self.out.ix, self.ix, phase_deg))?;
         }
         Ok(())
     }
 
+    /// Reads back what the node is actually generating, after the device has rounded the settings.
+    /// Custom waveform samples can't be read back and are left empty.
+    pub fn function(&self) -> Result<AnalogOutFunction> {
+        unsafe {
+            let mut func: FUNC = 0;
+            let (mut frequency, mut amplitude, mut offset, mut symmetry, mut phase_deg): (f64, f64, f64, f64, f64) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            handle_dwf_errors(FDwfAnalogOutNodeFunctionGet(self.out.device.handle, self.out.ix, self.ix, &mut func as *mut FUNC))?;
+            handle_dwf_errors(FDwfAnalogOutNodeFrequencyGet(self.out.device.handle, self.out.ix, self.ix, &mut frequency as *mut f64))?;
+            handle_dwf_errors(FDwfAnalogOutNodeAmplitudeGet(self.out.device.handle, self.out.ix, self.ix, &mut amplitude as *mut f64))?;
+            handle_dwf_errors(FDwfAnalogOutNodeOffsetGet(self.out.device.handle, self.out.ix, self.ix, &mut offset as *mut f64))?;
+            handle_dwf_errors(FDwfAnalogOutNodeSymmetryGet(self.out.device.handle, self.out.ix, self.ix, &mut symmetry as *mut f64))?;
+            handle_dwf_errors(FDwfAnalogOutNodePhaseGet(self.out.device.handle, self.out.ix, self.ix, &mut phase_deg as *mut f64))?;
+            Ok(match func {
+                funcDC => AnalogOutFunction::Const { offset },
+                funcSine => AnalogOutFunction::Sine { frequency, amplitude, offset, symmetry, phase_deg },
+                funcSquare => AnalogOutFunction::Square { frequency, amplitude, offset, symmetry, phase_deg },
+                funcTriangle => AnalogOutFunction::Triangle { frequency, amplitude, offset, symmetry, phase_deg },
+                funcRampUp => AnalogOutFunction::RampUp { frequency, amplitude, offset, symmetry, phase_deg },
+                funcRampDown => AnalogOutFunction::RampDown { frequency, amplitude, offset, symmetry, phase_deg },
+                funcNoise => AnalogOutFunction::Noise { frequency, amplitude, offset },
+                funcPulse => AnalogOutFunction::Pulse { frequency, amplitude, offset, symmetry, phase_deg },
+                funcTrapezium => AnalogOutFunction::Trapezium { frequency, amplitude, offset, symmetry, phase_deg },
+                funcSinePower => AnalogOutFunction::SinePower { frequency, amplitude, offset, symmetry, phase_deg },
+                funcCustom => AnalogOutFunction::Custom { samples: Vec::new(), frequency, amplitude, offset, phase_deg },
+                _ => return Err(Error::new(ErrorKind::NotSupported, &format!("function #{} has no AnalogOutFunction counterpart", func))),
+            })
+        }
+    }
+
     pub fn set_enabled(&self, enabled: bool) -> Result<()> {
         unsafe {
             handle_dwf_errors(FDwfAnalogOutNodeEnableSet(self.out.device.handle, self.out.ix, self.ix, to_c_bool(enabled)))?;
@@ -357,6 +555,7 @@ impl<'a> AnalogOutNode<'a> {
     }
 }
 
+#[derive(Clone, PartialEq, Debug)]
 pub enum AnalogOutFunction {
     Const { offset: f64 },
     RampUp { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
@@ -364,6 +563,27 @@ pub enum AnalogOutFunction {
     Sine { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
     Square { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
     Triangle { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
+    Noise { frequency: f64, amplitude: f64, offset: f64 },
+    Pulse { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
+    Trapezium { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
+    SinePower { frequency: f64, amplitude: f64, offset: f64, symmetry: f64, phase_deg: f64 },
+    /// One period of an arbitrary waveform, samples are scaled by `amplitude` and expected to be in the -1..1 range
+    Custom { samples: Vec<f64>, frequency: f64, amplitude: f64, offset: f64, phase_deg: f64 },
+}
+
+impl AnalogOutFunction {
+    /// Custom waveform with `samples` in volts, scaled to the -1..1 range so that nothing gets clipped
+    pub fn normalized_custom(samples: &[f64], frequency: f64, offset: f64, phase_deg: f64) -> AnalogOutFunction {
+        let peak = samples.iter().fold(0.0, |peak: f64, sample| peak.max(sample.abs()));
+        let scale = if peak > 0.0 { peak } else { 1.0 };
+        AnalogOutFunction::Custom {
+            samples: samples.iter().map(|sample| sample / scale).collect(),
+            frequency,
+            amplitude: scale,
+            offset,
+            phase_deg,
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -384,249 +604,3703 @@ impl AnalogOutIdleMode {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum AnalogOutMode {
+    Voltage = DwfAnalogOutModeVoltage as isize,
+    Current = DwfAnalogOutModeCurrent as isize,
+}
+
+impl AnalogOutMode {
+    fn code(self) -> DwfAnalogOutMode {
+        self as DwfAnalogOutMode
+    }
+
+    fn from_code(code: DwfAnalogOutMode) -> Result<AnalogOutMode> {
+        match code {
+            DwfAnalogOutModeVoltage => Ok(AnalogOutMode::Voltage),
+            DwfAnalogOutModeCurrent => Ok(AnalogOutMode::Current),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown output mode #{}", code))),
+        }
+    }
+}
+
 pub struct AnalogOut<'a> {
     device: &'a Device,
     ix: c_int,
 }
 
 impl<'a> AnalogOut<'a> {
-    pub fn node(&self, ix: u32) -> AnalogOutNode {
+    pub fn node(&self, kind: AnalogOutNodeKind) -> AnalogOutNode {
         AnalogOutNode {
             out: &self,
-            ix: ix as c_int,
+            ix: kind.code(),
         }
     }
 
-    pub fn set_duration(&self, duration: Duration) -> Result<()> {
+    pub fn get_available_nodes(&self) -> Result<Vec<AnalogOutNodeKind>> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogOutRunSet(self.device.handle, self.ix, duration.num_nanoseconds().unwrap() as f64 / 1e9))?;
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfAnalogOutNodeInfo(self.device.handle, self.ix, &mut supported as *mut c_int))?;
+            Ok([AnalogOutNodeKind::Carrier, AnalogOutNodeKind::FM, AnalogOutNodeKind::AM].iter()
+                .cloned()
+                .filter(|kind| is_bit_set(supported, kind.code()))
+                .collect())
         }
-        Ok(())
     }
 
-    pub fn set_repeat_count(&self, repeat_cnt: i32) -> Result<()> {
-        unsafe {
-            handle_dwf_errors(FDwfAnalogOutRepeatSet(self.device.handle, self.ix, repeat_cnt))?;
+    fn set_modulation(&self, kind: AnalogOutNodeKind, func: Option<AnalogOutFunction>) -> Result<()> {
+        if !self.get_available_nodes()?.contains(&kind) {
+            return Err(Error::new(ErrorKind::NotSupported, &format!("channel #{} has no {:?} node", self.ix, kind)));
+        }
+        let node = self.node(kind);
+        match func {
+            Some(func) => {
+                node.set_function(func)?;
+                node.set_enabled(true)
+            },
+            None => node.set_enabled(false),
         }
-        Ok(())
     }
 
-    pub fn set_trigger_source(&self, src: TriggerSource) -> Result<()> {
+    /// Like `AnalogOutNode::set_function`, fails on custom samples outside the -1..1 range
+    /// instead of silently clipping them. `node(kind).set_custom` clips them and returns the count.
+    pub fn set_carrier(&self, func: AnalogOutFunction) -> Result<()> {
+        self.set_modulation(AnalogOutNodeKind::Carrier, Some(func))
+    }
+
+    /// Frequency modulation, the amplitude of `func` is the deviation in percent of the carrier frequency.
+    /// `None` disables it. Fails on custom samples outside the -1..1 range, like `set_carrier`.
+    pub fn set_frequency_modulation(&self, func: Option<AnalogOutFunction>) -> Result<()> {
+        self.set_modulation(AnalogOutNodeKind::FM, func)
+    }
+
+    /// Amplitude modulation, the amplitude of `func` is the modulation index in percent.
+    /// `None` disables it. Fails on custom samples outside the -1..1 range, like `set_carrier`.
+    pub fn set_amplitude_modulation(&self, func: Option<AnalogOutFunction>) -> Result<()> {
+        self.set_modulation(AnalogOutNodeKind::AM, func)
+    }
+
+    /// Needed for custom AM/FM on Electronics Explorer
+    pub fn set_custom_am_fm_enabled(&self, enabled: bool) -> Result<()> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogOutTriggerSourceSet(self.device.handle, self.ix, src.code()))?;
+            handle_dwf_errors(FDwfAnalogOutCustomAMFMEnableSet(self.device.handle, self.ix, to_c_bool(enabled)))?;
         }
         Ok(())
     }
 
-    pub fn set_idle_mode(&self, mode: AnalogOutIdleMode) -> Result<()> {
+    /// Makes this channel start and stop together with `master`
+    pub fn set_master(&self, master: &AnalogOut) -> Result<()> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogOutIdleSet(self.device.handle, self.ix, mode.code()))?;
+            handle_dwf_errors(FDwfAnalogOutMasterSet(self.device.handle, self.ix, master.ix))?;
         }
         Ok(())
     }
 
-    pub fn start(&self) -> Result<()> {
+    pub fn get_master(&self) -> Result<AnalogOut<'a>> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogOutConfigure(self.device.handle, self.ix, to_c_bool(true)))?;
+            let mut master: c_int = 0;
+            handle_dwf_errors(FDwfAnalogOutMasterGet(self.device.handle, self.ix, &mut master as *mut c_int))?;
+            Ok(AnalogOut {
+                device: self.device,
+                ix: master,
+            })
         }
-        Ok(())
     }
 
-    pub fn stop(&self) -> Result<()> {
+    /// Shortest and longest run duration, zero means running until stopped
+    pub fn get_duration_limits(&self) -> Result<(Duration, Duration)> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogOutConfigure(self.device.handle, self.ix, to_c_bool(false)))?;
+            let (mut min, mut max): (f64, f64) = (0.0, 0.0);
+            handle_dwf_errors(FDwfAnalogOutRunInfo(self.device.handle, self.ix, &mut min as *mut f64, &mut max as *mut f64))?;
+            Ok((from_seconds(min), from_seconds(max)))
         }
-        Ok(())
     }
-}
-
-pub struct AnalogIO<'a> {
-    device: &'a Device,
-}
 
-impl<'a> AnalogIO<'a> {
-    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+    /// Zero runs until stopped
+    pub fn set_duration(&self, duration: Duration) -> Result<()> {
+        let (min, max) = self.get_duration_limits()?;
+        if duration != Duration::zero() && (duration < min || duration > max) {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("run duration {} is out of the [{}, {}] range", duration, min, max)));
+        }
         unsafe {
-            handle_dwf_errors(FDwfAnalogIOEnableSet(self.device.handle, to_c_bool(enabled)))?;
+            handle_dwf_errors(FDwfAnalogOutRunSet(self.device.handle, self.ix, to_seconds(duration)))?;
         }
         Ok(())
     }
 
-    pub fn channel(&self, ix: i32) -> AnalogIOChannel {
-        AnalogIOChannel {
-            io: self,
-            ix: ix as c_int,
+    /// Time left to run in the current repeat, refreshed by `get_status`
+    pub fn get_remaining_duration(&self) -> Result<Duration> {
+        unsafe {
+            let mut secs: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogOutRunStatus(self.device.handle, self.ix, &mut secs as *mut f64))?;
+            Ok(from_seconds(secs))
         }
     }
-}
-
-pub struct AnalogIOChannel<'a> {
-    io: &'a AnalogIO<'a>,
-    ix: c_int,
-}
 
-impl<'a> AnalogIOChannel<'a> {
-    pub fn node(&self, ix: i32) -> AnalogIOChannelNode {
-        AnalogIOChannelNode {
-            channel: self,
-            ix: ix as c_int,
+    pub fn get_wait_limits(&self) -> Result<(Duration, Duration)> {
+        unsafe {
+            let (mut min, mut max): (f64, f64) = (0.0, 0.0);
+            handle_dwf_errors(FDwfAnalogOutWaitInfo(self.device.handle, self.ix, &mut min as *mut f64, &mut max as *mut f64))?;
+            Ok((from_seconds(min), from_seconds(max)))
         }
     }
-}
-
-pub struct AnalogIOChannelNode<'a> {
-    channel: &'a AnalogIOChannel<'a>,
-    ix: c_int,
-}
 
-impl<'a> AnalogIOChannelNode<'a> {
-    pub fn set_value(&self, value: f64) -> Result<()> {
+    /// Delay between the trigger and the start of each run
+    pub fn set_wait(&self, wait: Duration) -> Result<()> {
+        let (min, max) = self.get_wait_limits()?;
+        if wait < min || wait > max {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("wait {} is out of the [{}, {}] range", wait, min, max)));
+        }
         unsafe {
-            handle_dwf_errors(FDwfAnalogIOChannelNodeSet(self.channel.io.device.handle, self.channel.ix, self.ix, value))?;
+            handle_dwf_errors(FDwfAnalogOutWaitSet(self.device.handle, self.ix, to_seconds(wait)))?;
         }
         Ok(())
     }
-}
-
 
-pub struct AnalogIn<'a> {
-    device: &'a Device,
-}
-
-impl<'a> AnalogIn<'a> {
-    pub fn start(&self) -> Result<()> {
+    /// Zero repeat count means repeating forever
+    pub fn get_repeat_count_limits(&self) -> Result<(i32, i32)> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogInConfigure(self.device.handle, to_c_bool(false), to_c_bool(true)))?;
+            let (mut min, mut max): (c_int, c_int) = (0, 0);
+            handle_dwf_errors(FDwfAnalogOutRepeatInfo(self.device.handle, self.ix, &mut min as *mut c_int, &mut max as *mut c_int))?;
+            Ok((min, max))
         }
-        Ok(())
     }
 
-    pub fn set_frequency(&self, freq: f64) -> Result<()> {
+    /// Zero repeats forever
+    pub fn set_repeat_count(&self, repeat_cnt: i32) -> Result<()> {
+        let (min, max) = self.get_repeat_count_limits()?;
+        if repeat_cnt != 0 && (repeat_cnt < min || repeat_cnt > max) {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("repeat count {} is out of the [{}, {}] range", repeat_cnt, min, max)));
+        }
         unsafe {
-            handle_dwf_errors(FDwfAnalogInFrequencySet(self.device.handle, freq))?;
+            handle_dwf_errors(FDwfAnalogOutRepeatSet(self.device.handle, self.ix, repeat_cnt))?;
         }
         Ok(())
     }
 
-    pub fn set_buffer_size(&self, buf_size: u32) -> Result<()> {
+    /// Repeats left to run, refreshed by `get_status`
+    pub fn get_remaining_repeats(&self) -> Result<i32> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogInBufferSizeSet(self.device.handle, buf_size as i32))?;
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogOutRepeatStatus(self.device.handle, self.ix, &mut cnt as *mut c_int))?;
+            Ok(cnt)
         }
-        Ok(())
     }
 
-    pub fn set_record_mode(&self, length: f64) -> Result<()> {
+    /// Waits for the trigger before each repeat, not just the first one
+    pub fn set_repeat_on_trigger(&self, enabled: bool) -> Result<()> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogInRecordLengthSet(self.device.handle, length))?;
-            handle_dwf_errors(FDwfAnalogInAcquisitionModeSet(self.device.handle, acqmodeRecord as ACQMODE))?;
+            handle_dwf_errors(FDwfAnalogOutRepeatTriggerSet(self.device.handle, self.ix, to_c_bool(enabled)))?;
         }
         Ok(())
     }
 
-    pub fn channel(&self, ix: i32) -> AnalogInChannel {
-        AnalogInChannel {
-            input: &self,
-            ix: ix as c_int,
+    pub fn set_trigger_source(&self, src: TriggerSource) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogOutTriggerSourceSet(self.device.handle, self.ix, src.code()))?;
         }
+        Ok(())
     }
 
-    pub fn get_status(&self) -> Result<AnalogAcquisitionStatus> {
+    pub fn set_trigger_slope(&self, slope: TriggerSlope) -> Result<()> {
         unsafe {
-            let mut state: DwfState = mem::uninitialized();
-            handle_dwf_errors(FDwfAnalogInStatus(self.device.handle, to_c_bool(true), (&mut state) as *mut DwfState))?;
-            Ok(match state {
-                DwfStateReady => AnalogAcquisitionStatus::Ready,
-                DwfStateConfig => AnalogAcquisitionStatus::Config,
-                DwfStatePrefill => AnalogAcquisitionStatus::Prefill,
-                DwfStateArmed => AnalogAcquisitionStatus::Armed,
-                DwfStateWait => AnalogAcquisitionStatus::Waiting,
-                DwfStateRunning => AnalogAcquisitionStatus::Running,
-                DwfStateDone => AnalogAcquisitionStatus::Done,
-                _ => panic!(),
-            })
+            handle_dwf_errors(FDwfAnalogOutTriggerSlopeSet(self.device.handle, self.ix, slope.code()))?;
         }
+        Ok(())
     }
 
-    pub fn get_samples_left(&self) -> Result<i32> {
+    pub fn get_trigger_slope(&self) -> Result<TriggerSlope> {
         unsafe {
-            let mut ret = mem::uninitialized();
-            handle_dwf_errors(FDwfAnalogInStatusSamplesLeft(self.device.handle, &mut ret as *mut c_int))?;
-            Ok(ret)
+            let mut slope: DwfTriggerSlope = 0;
+            handle_dwf_errors(FDwfAnalogOutTriggerSlopeGet(self.device.handle, self.ix, &mut slope as *mut DwfTriggerSlope))?;
+            TriggerSlope::from_code(slope)
         }
     }
 
-    pub fn get_record_status(&self) -> Result<(i32, i32, i32)> {
+    pub fn set_idle_mode(&self, mode: AnalogOutIdleMode) -> Result<()> {
         unsafe {
-            let (mut available, mut lost, mut corrupted) = mem::uninitialized();
-            handle_dwf_errors(FDwfAnalogInStatusRecord(self.device.handle,
-                                                       &mut available as *mut c_int,
-                                                       &mut lost as *mut c_int,
-                                                       &mut corrupted as *mut c_int
-            ))?;
-            Ok((available, lost, corrupted))
+            handle_dwf_errors(FDwfAnalogOutIdleSet(self.device.handle, self.ix, mode.code()))?;
         }
+        Ok(())
     }
-}
-
-#[derive(Eq, PartialEq, Debug)]
-pub enum AnalogAcquisitionStatus {
-    Ready = DwfStateReady as isize,
-    Config = DwfStateConfig as isize,
-    Prefill = DwfStatePrefill as isize,
-    Armed = DwfStateArmed as isize,
-    Waiting = DwfStateWait as isize,
-    Running = DwfStateRunning as isize,
-    Done = DwfStateDone as isize,
-}
-
-pub struct AnalogInChannel<'a> {
-    input: &'a AnalogIn<'a>,
-    ix: c_int,
-}
 
-impl<'a> AnalogInChannel<'a> {
-    pub fn set_offset(&self, offset: f64) -> Result<()> {
+    pub fn start(&self) -> Result<()> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogInChannelOffsetSet(self.input.device.handle, self.ix, offset))?;
+            handle_dwf_errors(FDwfAnalogOutConfigure(self.device.handle, self.ix, to_c_bool(true)))?;
         }
         Ok(())
     }
 
-    pub fn set_range(&self, range: f64) -> Result<()> {
+    pub fn stop(&self) -> Result<()> {
         unsafe {
-            handle_dwf_errors(FDwfAnalogInChannelRangeSet(self.input.device.handle, self.ix, range))?;
+            handle_dwf_errors(FDwfAnalogOutConfigure(self.device.handle, self.ix, to_c_bool(false)))?;
         }
         Ok(())
     }
 
-    pub fn fetch_samples(&self, dest: &mut Vec<f64>, available: i32) -> Result<()> {
+    /// Only supported on Electronics Explorer channels 3 and 4
+    pub fn set_mode(&self, mode: AnalogOutMode) -> Result<()> {
         unsafe {
-            let original_len = dest.len();
-            dest.reserve(available as usize);
-            dest.set_len(original_len + available as usize);
-            handle_dwf_errors(FDwfAnalogInStatusData(self.input.device.handle, self.ix,
-                                                     dest.as_mut_ptr().offset(original_len as isize), available))?;
+            handle_dwf_errors(FDwfAnalogOutModeSet(self.device.handle, self.ix, mode.code()))?;
         }
         Ok(())
     }
-}
-
-
-pub struct Device {
-    handle: HDWF,
-}
 
-impl Device {
-    pub fn set_auto_configure(&self, enabled: bool) -> Result<()> {
+    pub fn get_mode(&self) -> Result<AnalogOutMode> {
         unsafe {
-            handle_dwf_errors(FDwfDeviceAutoConfigureSet(self.handle, to_c_bool(enabled)))?;
+            let mut mode: DwfAnalogOutMode = 0;
+            handle_dwf_errors(FDwfAnalogOutModeGet(self.device.handle, self.ix, &mut mode as *mut DwfAnalogOutMode))?;
+            AnalogOutMode::from_code(mode)
         }
-        Ok(())
     }
 
-    pub fn reset(&self) -> Result<()> {
+    pub fn get_limitation_limits(&self) -> Result<(f64, f64)> {
         unsafe {
-            handle_dwf_errors(FDwfDeviceReset(self.handle))?;
+            let (mut min, mut max): (f64, f64) = (0.0, 0.0);
+            handle_dwf_errors(FDwfAnalogOutLimitationInfo(self.device.handle, self.ix, &mut min as *mut f64, &mut max as *mut f64))?;
+            Ok((min, max))
+        }
+    }
+
+    /// Current limit in amps in the voltage mode, voltage limit in volts in the current mode
+    pub fn set_limitation(&self, limit: f64) -> Result<()> {
+        let (min, max) = self.get_limitation_limits()?;
+        if limit < min || limit > max {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("limitation {} is out of the [{}, {}] range", limit, min, max)));
+        }
+        unsafe {
+            handle_dwf_errors(FDwfAnalogOutLimitationSet(self.device.handle, self.ix, limit))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_limitation(&self) -> Result<f64> {
+        unsafe {
+            let mut limit: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogOutLimitationGet(self.device.handle, self.ix, &mut limit as *mut f64))?;
+            Ok(limit)
+        }
+    }
+
+    /// This channel as a programmable supply
+    pub fn supply(&self) -> AnalogOutSupply {
+        AnalogOutSupply {
+            out: &self,
+        }
+    }
+
+    pub fn get_status(&self) -> Result<GeneratorStatus> {
+        unsafe {
+            let mut state: DwfState = 0;
+            handle_dwf_errors(FDwfAnalogOutStatus(self.device.handle, self.ix, &mut state as *mut DwfState))?;
+            GeneratorStatus::from_code(state)
+        }
+    }
+
+    /// Streams `samples` in the -1..1 range, scaled by `amplitude`, at `sample_rate` through the carrier node.
+    /// Prefills the device buffer and starts the channel; keep calling `AnalogOutPlayer::poll` to top it up.
+    /// `poll` blocks until the iterator yields all the samples the device can take, use `play_channel`
+    /// for a producer running in real time.
+    pub fn play<I: Iterator<Item = f64>>(&self, samples: I, sample_rate: f64, amplitude: f64, offset: f64)
+        -> Result<AnalogOutPlayer<std::iter::Fuse<I>>> {
+        self.start_player(samples.fuse(), sample_rate, amplitude, offset)
+    }
+
+    /// Like `play`, but `poll` never waits for `receiver`, it hands over whatever is queued.
+    /// Playback finishes once all the senders are dropped and the queue is drained.
+    pub fn play_channel(&self, receiver: Receiver<f64>, sample_rate: f64, amplitude: f64, offset: f64)
+        -> Result<AnalogOutPlayer<Receiver<f64>>> {
+        self.start_player(receiver, sample_rate, amplitude, offset)
+    }
+
+    fn start_player<S: play_source::Source>(&self, samples: S, sample_rate: f64, amplitude: f64, offset: f64) -> Result<AnalogOutPlayer<S>> {
+        let mut player = AnalogOutPlayer {
+            out: &self,
+            samples,
+            buf: Vec::new(),
+            exhausted: false,
+            clipped: 0,
+        };
+        unsafe {
+            handle_dwf_errors(FDwfAnalogOutNodeEnableSet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(), to_c_bool(true)))?;
+            handle_dwf_errors(FDwfAnalogOutNodeFunctionSet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(), funcPlay))?;
+            handle_dwf_errors(FDwfAnalogOutNodeFrequencySet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(), sample_rate))?;
+            handle_dwf_errors(FDwfAnalogOutNodeAmplitudeSet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(), amplitude))?;
+            handle_dwf_errors(FDwfAnalogOutNodeOffsetSet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(), offset))?;
+            // the buffer size depends on the node function, query it once play mode is set
+            let (mut min, mut max): (c_int, c_int) = (0, 0);
+            handle_dwf_errors(FDwfAnalogOutNodeDataInfo(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(),
+                                                        &mut min as *mut c_int, &mut max as *mut c_int))?;
+            player.fill(max.max(min) as usize)?;
+            if !player.buf.is_empty() {
+                handle_dwf_errors(FDwfAnalogOutNodeDataSet(self.device.handle, self.ix, AnalogOutNodeKind::Carrier.code(),
+                                                           player.buf.as_mut_ptr(), player.buf.len() as c_int))?;
+            }
+        }
+        self.start()?;
+        Ok(player)
+    }
+}
+
+/// Electronics Explorer channels 3 and 4 sourcing a constant voltage or current
+pub struct AnalogOutSupply<'a> {
+    out: &'a AnalogOut<'a>,
+}
+
+impl<'a> AnalogOutSupply<'a> {
+    fn source(&self, mode: AnalogOutMode, level: f64, limit: f64) -> Result<()> {
+        self.out.set_mode(mode)?;
+        self.out.set_limitation(limit)?;
+        self.out.set_carrier(AnalogOutFunction::Const { offset: level })
+    }
+
+    /// Sources `volts`, limiting the current to `current_limit` amps
+    pub fn set_voltage(&self, volts: f64, current_limit: f64) -> Result<()> {
+        self.source(AnalogOutMode::Voltage, volts, current_limit)
+    }
+
+    /// Sources `amps`, limiting the voltage to `voltage_limit` volts
+    pub fn set_current(&self, amps: f64, voltage_limit: f64) -> Result<()> {
+        self.source(AnalogOutMode::Current, amps, voltage_limit)
+    }
+
+    pub fn get_mode(&self) -> Result<AnalogOutMode> {
+        self.out.get_mode()
+    }
+
+    pub fn start(&self) -> Result<()> {
+        self.out.start()
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.out.stop()
+    }
+}
+
+/// Channels started and stopped together from one master, so the `phase_deg` offsets between them hold
+pub struct AnalogOutGroup<'a> {
+    channels: Vec<AnalogOut<'a>>,
+}
+
+impl<'a> AnalogOutGroup<'a> {
+    /// The master comes first
+    pub fn channels(&self) -> &[AnalogOut<'a>] {
+        &self.channels
+    }
+
+    pub fn master(&self) -> &AnalogOut<'a> {
+        &self.channels[0]
+    }
+
+    fn check_links(&self) -> Result<()> {
+        for channel in &self.channels {
+            if channel.get_master()?.ix != self.master().ix {
+                return Err(Error::new(ErrorKind::InvalidParameter2,
+                                      &format!("channel #{} doesn't follow channel #{} anymore", channel.ix, self.master().ix)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Restarts all the channels from the same instant
+    pub fn start(&self) -> Result<()> {
+        self.check_links()?;
+        // a follower which was already running would keep its own phase
+        for channel in &self.channels {
+            channel.stop()?;
+        }
+        self.master().start()
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.master().stop()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PlayEvent {
+    Playing,
+    /// The device ran out of data (`lost`) or received it too late (`corrupted`) since the last poll
+    Underrun { lost: u32, corrupted: u32 },
+    /// Samples outside the -1..1 range were clipped since the last reported event
+    Clipped { count: u32 },
+    /// All the samples were handed over to the device
+    Finished,
+}
+
+mod play_source {
+    use std::sync::mpsc::{Receiver, TryRecvError};
+
+    /// Where `AnalogOutPlayer` takes samples from
+    pub trait Source {
+        /// Appends up to `cnt` samples to `buf`, returns `false` once there will be no more
+        fn take_into(&mut self, buf: &mut Vec<f64>, cnt: usize) -> bool;
+    }
+
+    impl<I: Iterator<Item = f64>> Source for std::iter::Fuse<I> {
+        fn take_into(&mut self, buf: &mut Vec<f64>, cnt: usize) -> bool {
+            buf.extend(self.by_ref().take(cnt));
+            buf.len() == cnt
+        }
+    }
+
+    impl Source for Receiver<f64> {
+        fn take_into(&mut self, buf: &mut Vec<f64>, cnt: usize) -> bool {
+            while buf.len() < cnt {
+                match self.try_recv() {
+                    Ok(sample) => buf.push(sample),
+                    Err(TryRecvError::Empty) => return true,
+                    Err(TryRecvError::Disconnected) => return false,
+                }
+            }
+            true
+        }
+    }
+}
+
+pub struct AnalogOutPlayer<'a, S: play_source::Source> {
+    out: &'a AnalogOut<'a>,
+    samples: S,
+    buf: Vec<f64>,
+    exhausted: bool,
+    /// Not reported yet
+    clipped: u32,
+}
+
+impl<'a, S: play_source::Source> AnalogOutPlayer<'a, S> {
+    fn fill(&mut self, cnt: usize) -> Result<()> {
+        self.buf.clear();
+        if !self.samples.take_into(&mut self.buf, cnt) {
+            self.exhausted = true;
+        }
+        for sample in self.buf.iter_mut() {
+            if !sample.is_finite() {
+                return Err(Error::new(ErrorKind::InvalidParameter4, &format!("can't play a {} sample", sample)));
+            }
+            if sample.abs() > 1.0 {
+                *sample = sample.max(-1.0).min(1.0);
+                self.clipped += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tops up the device buffer with as many samples as it can take
+    pub fn poll(&mut self) -> Result<PlayEvent> {
+        if !self.exhausted {
+            // the play status is only refreshed by a status poll
+            self.out.get_status()?;
+            let (mut free, mut lost, mut corrupted): (c_int, c_int, c_int) = (0, 0, 0);
+            unsafe {
+                handle_dwf_errors(FDwfAnalogOutNodePlayStatus(self.out.device.handle, self.out.ix, AnalogOutNodeKind::Carrier.code(),
+                                                              &mut free as *mut c_int,
+                                                              &mut lost as *mut c_int,
+                                                              &mut corrupted as *mut c_int))?;
+            }
+            if free > 0 {
+                self.fill(free as usize)?;
+                if !self.buf.is_empty() {
+                    unsafe {
+                        handle_dwf_errors(FDwfAnalogOutNodePlayData(self.out.device.handle, self.out.ix, AnalogOutNodeKind::Carrier.code(),
+                                                                    self.buf.as_mut_ptr(), self.buf.len() as c_int))?;
+                    }
+                }
+            }
+            if lost > 0 || corrupted > 0 {
+                return Ok(PlayEvent::Underrun { lost: lost as u32, corrupted: corrupted as u32 });
+            }
+        }
+        Ok(if self.clipped > 0 {
+            let count = self.clipped;
+            self.clipped = 0;
+            PlayEvent::Clipped { count }
+        } else if self.exhausted {
+            PlayEvent::Finished
+        } else {
+            PlayEvent::Playing
+        })
+    }
+
+    /// Keeps polling until all the samples are handed over, reporting underruns and clipping to `on_event`
+    pub fn run<F: FnMut(PlayEvent)>(&mut self, mut on_event: F) -> Result<()> {
+        loop {
+            match self.poll()? {
+                PlayEvent::Playing => std::thread::sleep(std::time::Duration::from_millis(1)),
+                PlayEvent::Finished => return Ok(()),
+                event => on_event(event),
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AnalogIONodeKind {
+    Enable,
+    Voltage,
+    Current,
+    Power,
+    Temperature,
+    /// A kind this crate doesn't know about, reported by a newer runtime
+    Other(u8),
+}
+
+impl AnalogIONodeKind {
+    fn from_code(code: ANALOGIO) -> AnalogIONodeKind {
+        match code {
+            analogioEnable => AnalogIONodeKind::Enable,
+            analogioVoltage => AnalogIONodeKind::Voltage,
+            analogioCurrent => AnalogIONodeKind::Current,
+            analogioPower => AnalogIONodeKind::Power,
+            analogioTemperature => AnalogIONodeKind::Temperature,
+            _ => AnalogIONodeKind::Other(code as u8),
+        }
+    }
+}
+
+/// Values a node can be set to or can report, `steps` is the number of distinct values
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AnalogIORange {
+    pub min: f64,
+    pub max: f64,
+    pub steps: u32,
+}
+
+impl AnalogIORange {
+    pub fn step(&self) -> f64 {
+        if self.steps > 1 {
+            (self.max - self.min) / (self.steps - 1) as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+pub struct AnalogIO<'a> {
+    device: &'a Device,
+}
+
+impl<'a> AnalogIO<'a> {
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogIOReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    /// Applies the settings, needed only when auto configuration is disabled
+    pub fn configure(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogIOConfigure(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the measured values of all the nodes from the device
+    pub fn read_status(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogIOStatus(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogIOEnableSet(self.device.handle, to_c_bool(enabled)))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> Result<bool> {
+        unsafe {
+            let mut enabled: BOOL = 0;
+            handle_dwf_errors(FDwfAnalogIOEnableGet(self.device.handle, &mut enabled as *mut BOOL))?;
+            Ok(enabled != 0)
+        }
+    }
+
+    /// The master switch state as of the last `read_status`, it turns off on overcurrent
+    pub fn get_enabled_status(&self) -> Result<bool> {
+        unsafe {
+            let mut enabled: BOOL = 0;
+            handle_dwf_errors(FDwfAnalogIOEnableStatus(self.device.handle, &mut enabled as *mut BOOL))?;
+            Ok(enabled != 0)
+        }
+    }
+
+    pub fn channel_count(&self) -> Result<u32> {
+        unsafe {
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogIOChannelCount(self.device.handle, &mut cnt as *mut c_int))?;
+            Ok(cnt as u32)
+        }
+    }
+
+    pub fn channel(&self, ix: i32) -> AnalogIOChannel {
+        AnalogIOChannel {
+            io: self,
+            ix: ix as c_int,
+        }
+    }
+
+    pub fn channels(&self) -> Result<Vec<AnalogIOChannel>> {
+        Ok((0..self.channel_count()? as i32).map(|ix| self.channel(ix)).collect())
+    }
+}
+
+pub struct AnalogIOChannel<'a> {
+    io: &'a AnalogIO<'a>,
+    ix: c_int,
+}
+
+impl<'a> AnalogIOChannel<'a> {
+    fn get_name_and_label(&self) -> Result<(String, String)> {
+        unsafe {
+            let mut name = [0 as c_char; 32];
+            let mut label = [0 as c_char; 16];
+            handle_dwf_errors(FDwfAnalogIOChannelName(self.io.device.handle, self.ix, name.as_mut_ptr(), label.as_mut_ptr()))?;
+            Ok((CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned(),
+                CStr::from_ptr(label.as_ptr()).to_string_lossy().into_owned()))
+        }
+    }
+
+    /// E.g. "Positive Supply"
+    pub fn get_name(&self) -> Result<String> {
+        Ok(self.get_name_and_label()?.0)
+    }
+
+    /// E.g. "V+"
+    pub fn get_label(&self) -> Result<String> {
+        Ok(self.get_name_and_label()?.1)
+    }
+
+    pub fn node_count(&self) -> Result<u32> {
+        unsafe {
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogIOChannelInfo(self.io.device.handle, self.ix, &mut cnt as *mut c_int))?;
+            Ok(cnt as u32)
+        }
+    }
+
+    pub fn node(&self, ix: i32) -> AnalogIOChannelNode {
+        AnalogIOChannelNode {
+            channel: self,
+            ix: ix as c_int,
+        }
+    }
+
+    pub fn nodes(&self) -> Result<Vec<AnalogIOChannelNode>> {
+        Ok((0..self.node_count()? as i32).map(|ix| self.node(ix)).collect())
+    }
+
+    /// The first node of the given kind
+    pub fn find_node(&self, kind: AnalogIONodeKind) -> Result<Option<AnalogIOChannelNode>> {
+        for node in self.nodes()? {
+            if node.get_kind()? == kind {
+                return Ok(Some(node));
+            }
+        }
+        Ok(None)
+    }
+}
+
+pub struct AnalogIOChannelNode<'a> {
+    channel: &'a AnalogIOChannel<'a>,
+    ix: c_int,
+}
+
+impl<'a> AnalogIOChannelNode<'a> {
+    fn get_name_and_units(&self) -> Result<(String, String)> {
+        unsafe {
+            let mut name = [0 as c_char; 32];
+            let mut units = [0 as c_char; 16];
+            handle_dwf_errors(FDwfAnalogIOChannelNodeName(self.channel.io.device.handle, self.channel.ix, self.ix,
+                                                          name.as_mut_ptr(), units.as_mut_ptr()))?;
+            Ok((CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned(),
+                CStr::from_ptr(units.as_ptr()).to_string_lossy().into_owned()))
+        }
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        Ok(self.get_name_and_units()?.0)
+    }
+
+    pub fn get_units(&self) -> Result<String> {
+        Ok(self.get_name_and_units()?.1)
+    }
+
+    pub fn get_kind(&self) -> Result<AnalogIONodeKind> {
+        unsafe {
+            let mut kind: ANALOGIO = 0;
+            handle_dwf_errors(FDwfAnalogIOChannelNodeInfo(self.channel.io.device.handle, self.channel.ix, self.ix, &mut kind as *mut ANALOGIO))?;
+            Ok(AnalogIONodeKind::from_code(kind))
+        }
+    }
+
+    /// `None` for read-only nodes
+    pub fn get_setpoint_range(&self) -> Result<Option<AnalogIORange>> {
+        unsafe {
+            let (mut min, mut max, mut steps): (f64, f64, c_int) = (0.0, 0.0, 0);
+            handle_dwf_errors(FDwfAnalogIOChannelNodeSetInfo(self.channel.io.device.handle, self.channel.ix, self.ix,
+                                                             &mut min as *mut f64, &mut max as *mut f64, &mut steps as *mut c_int))?;
+            Ok(if steps > 0 { Some(AnalogIORange { min, max, steps: steps as u32 }) } else { None })
+        }
+    }
+
+    /// `None` for write-only nodes
+    pub fn get_status_range(&self) -> Result<Option<AnalogIORange>> {
+        unsafe {
+            let (mut min, mut max, mut steps): (f64, f64, c_int) = (0.0, 0.0, 0);
+            handle_dwf_errors(FDwfAnalogIOChannelNodeStatusInfo(self.channel.io.device.handle, self.channel.ix, self.ix,
+                                                                &mut min as *mut f64, &mut max as *mut f64, &mut steps as *mut c_int))?;
+            Ok(if steps > 0 { Some(AnalogIORange { min, max, steps: steps as u32 }) } else { None })
+        }
+    }
+
+    pub fn set_value(&self, value: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogIOChannelNodeSet(self.channel.io.device.handle, self.channel.ix, self.ix, value))?;
+        }
+        Ok(())
+    }
+
+    /// The setpoint
+    pub fn get_value(&self) -> Result<f64> {
+        unsafe {
+            let mut value: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogIOChannelNodeGet(self.channel.io.device.handle, self.channel.ix, self.ix, &mut value as *mut f64))?;
+            Ok(value)
+        }
+    }
+
+    /// The measured value as of the last `AnalogIO::read_status`
+    pub fn get_last_status(&self) -> Result<f64> {
+        unsafe {
+            let mut value: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogIOChannelNodeStatus(self.channel.io.device.handle, self.channel.ix, self.ix, &mut value as *mut f64))?;
+            Ok(value)
+        }
+    }
+
+    /// Reads the measured value from the device
+    pub fn get_status(&self) -> Result<f64> {
+        self.channel.io.read_status()?;
+        self.get_last_status()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SupplyRail {
+    /// Analog Discovery V+
+    Positive,
+    /// Analog Discovery V-
+    Negative,
+    /// Digital Discovery VIO
+    DigitalIO,
+}
+
+impl SupplyRail {
+    pub fn label(self) -> &'static str {
+        match self {
+            SupplyRail::Positive => "V+",
+            SupplyRail::Negative => "V-",
+            SupplyRail::DigitalIO => "VIO",
+        }
+    }
+
+    fn for_device(id: DEVID) -> Option<&'static [SupplyRail]> {
+        match id {
+            devidDiscovery | devidDiscovery2 => Some(&[SupplyRail::Positive, SupplyRail::Negative]),
+            devidDDiscovery => Some(&[SupplyRail::DigitalIO]),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RailStatus {
+    pub rail: SupplyRail,
+    pub enabled: bool,
+    pub voltage: Option<f64>,
+    pub current: Option<f64>,
+}
+
+/// Voltage and current drawn from the USB port or the auxiliary supply
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SupplyMonitorStatus {
+    pub voltage: Option<f64>,
+    pub current: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PowerSuppliesStatus {
+    pub master_enabled: bool,
+    pub rails: Vec<RailStatus>,
+    pub usb: Option<SupplyMonitorStatus>,
+    pub aux: Option<SupplyMonitorStatus>,
+}
+
+/// The on-board supplies, on top of the AnalogIO channels
+pub struct PowerSupplies<'a> {
+    io: AnalogIO<'a>,
+    rails: Vec<(SupplyRail, i32)>,
+    usb: Option<i32>,
+    aux: Option<i32>,
+}
+
+impl<'a> PowerSupplies<'a> {
+    fn new(io: AnalogIO<'a>) -> Result<PowerSupplies<'a>> {
+        let known_rails = SupplyRail::for_device(io.device.id)
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, &format!("no known supplies on device #{}", io.device.id)))?;
+        let mut labels = Vec::new();
+        for channel in io.channels()? {
+            let (name, label) = channel.get_name_and_label()?;
+            labels.push((channel.ix, name, label));
+        }
+        let find = |label: &str| labels.iter()
+            .find(|&&(_, ref name, ref channel_label)| channel_label == label || name == label)
+            .map(|&(ix, _, _)| ix);
+        let mut rails = Vec::new();
+        for &rail in known_rails {
+            let ix = find(rail.label())
+                .ok_or_else(|| Error::new(ErrorKind::NotSupported, &format!("no {} channel", rail.label())))?;
+            rails.push((rail, ix));
+        }
+        let usb = find("USB");
+        let aux = find("AUX");
+        Ok(PowerSupplies { io, rails, usb, aux })
+    }
+
+    pub fn rails(&self) -> Vec<SupplyRail> {
+        self.rails.iter().map(|&(rail, _)| rail).collect()
+    }
+
+    fn channel(&self, rail: SupplyRail) -> Result<AnalogIOChannel> {
+        self.rails.iter()
+            .find(|&&(r, _)| r == rail)
+            .map(|&(_, ix)| self.io.channel(ix))
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, &format!("no {} supply on this device", rail.label())))
+    }
+
+    fn node<'c>(channel: &'c AnalogIOChannel, kind: AnalogIONodeKind) -> Result<AnalogIOChannelNode<'c>> {
+        channel.find_node(kind)?
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, &format!("channel #{} has no {:?} node", channel.ix, kind)))
+    }
+
+    /// `None` when the node is missing or can't be measured
+    fn read_node(channel: &AnalogIOChannel, kind: AnalogIONodeKind) -> Result<Option<f64>> {
+        if let Some(node) = channel.find_node(kind)? {
+            if node.get_status_range()?.is_some() {
+                return Ok(Some(node.get_last_status()?));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn get_voltage_range(&self, rail: SupplyRail) -> Result<AnalogIORange> {
+        let channel = self.channel(rail)?;
+        Self::node(&channel, AnalogIONodeKind::Voltage)?.get_setpoint_range()?
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, &format!("{} voltage can't be set", rail.label())))
+    }
+
+    pub fn set_voltage(&self, rail: SupplyRail, volts: f64) -> Result<()> {
+        let range = self.get_voltage_range(rail)?;
+        if !range.contains(volts) {
+            return Err(Error::new(ErrorKind::InvalidParameter4,
+                                  &format!("{} voltage {} is out of the [{}, {}] range", rail.label(), volts, range.min, range.max)));
+        }
+        let channel = self.channel(rail)?;
+        Self::node(&channel, AnalogIONodeKind::Voltage)?.set_value(volts)
+    }
+
+    /// The rail only turns on with the master switch on as well.
+    /// Rails without their own enable node follow the master switch, which this then sets instead
+    pub fn set_enabled(&self, rail: SupplyRail, enabled: bool) -> Result<()> {
+        let channel = self.channel(rail)?;
+        match channel.find_node(AnalogIONodeKind::Enable)? {
+            Some(enable) => enable.set_value(if enabled { 1.0 } else { 0.0 }),
+            None => self.io.set_enabled(enabled),
+        }
+    }
+
+    pub fn set_master_enabled(&self, enabled: bool) -> Result<()> {
+        self.io.set_enabled(enabled)
+    }
+
+    fn read_monitor(&self, ix: Option<i32>) -> Result<Option<SupplyMonitorStatus>> {
+        match ix {
+            Some(ix) => {
+                let channel = self.io.channel(ix);
+                Ok(Some(SupplyMonitorStatus {
+                    voltage: Self::read_node(&channel, AnalogIONodeKind::Voltage)?,
+                    current: Self::read_node(&channel, AnalogIONodeKind::Current)?,
+                }))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the measured values from the device
+    pub fn read_status(&self) -> Result<PowerSuppliesStatus> {
+        self.io.read_status()?;
+        let mut rails = Vec::with_capacity(self.rails.len());
+        for &(rail, ix) in &self.rails {
+            let channel = self.io.channel(ix);
+            let enabled = match channel.find_node(AnalogIONodeKind::Enable)? {
+                Some(enable) => match enable.get_status_range()? {
+                    Some(_) => enable.get_last_status()? != 0.0,
+                    None => enable.get_value()? != 0.0,
+                },
+                None => self.io.get_enabled_status()?,
+            };
+            rails.push(RailStatus {
+                rail,
+                enabled,
+                voltage: Self::read_node(&channel, AnalogIONodeKind::Voltage)?,
+                current: Self::read_node(&channel, AnalogIONodeKind::Current)?,
+            });
+        }
+        Ok(PowerSuppliesStatus {
+            master_enabled: self.io.get_enabled_status()?,
+            rails,
+            usb: self.read_monitor(self.usb)?,
+            aux: self.read_monitor(self.aux)?,
+        })
+    }
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum Filter {
+    Decimate = filterDecimate as isize,
+    Average = filterAverage as isize,
+    MinMax = filterMinMax as isize,
+}
+
+impl Filter {
+    fn code(self) -> FILTER {
+        self as FILTER
+    }
+
+    fn from_code(code: FILTER) -> Result<Filter> {
+        match code {
+            filterDecimate => Ok(Filter::Decimate),
+            filterAverage => Ok(Filter::Average),
+            filterMinMax => Ok(Filter::MinMax),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown filter #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum AnalogInTriggerType {
+    Edge = trigtypeEdge as isize,
+    Pulse = trigtypePulse as isize,
+    Transition = trigtypeTransition as isize,
+}
+
+impl AnalogInTriggerType {
+    fn code(self) -> TRIGTYPE {
+        self as TRIGTYPE
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum TriggerLengthCondition {
+    Less = triglenLess as isize,
+    Timeout = triglenTimeout as isize,
+    More = triglenMore as isize,
+}
+
+impl TriggerLengthCondition {
+    fn code(self) -> TRIGLEN {
+        self as TRIGLEN
+    }
+}
+
+/// Analog in trigger configuration, settings which are left out keep their current values
+#[derive(Clone, PartialEq, Debug)]
+pub struct AnalogInTrigger {
+    source: TriggerSource,
+    trigger_type: Option<AnalogInTriggerType>,
+    channel: Option<i32>,
+    filter: Option<Filter>,
+    level: Option<f64>,
+    hysteresis: Option<f64>,
+    condition: Option<TriggerSlope>,
+    length: Option<Duration>,
+    length_condition: Option<TriggerLengthCondition>,
+    hold_off: Option<Duration>,
+    position: Option<Duration>,
+    auto_timeout: Option<Duration>,
+}
+
+impl AnalogInTrigger {
+    pub fn new(source: TriggerSource) -> AnalogInTrigger {
+        AnalogInTrigger {
+            source,
+            trigger_type: None,
+            channel: None,
+            filter: None,
+            level: None,
+            hysteresis: None,
+            condition: None,
+            length: None,
+            length_condition: None,
+            hold_off: None,
+            position: None,
+            auto_timeout: None,
+        }
+    }
+
+    pub fn trigger_type(mut self, trigger_type: AnalogInTriggerType) -> Self {
+        self.trigger_type = Some(trigger_type);
+        self
+    }
+
+    pub fn channel(mut self, ix: i32) -> Self {
+        self.channel = Some(ix);
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Volts
+    pub fn level(mut self, level: f64) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Volts
+    pub fn hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = Some(hysteresis);
+        self
+    }
+
+    pub fn condition(mut self, condition: TriggerSlope) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    /// Pulse or transition length for the non-edge trigger types
+    pub fn length(mut self, length: Duration, condition: TriggerLengthCondition) -> Self {
+        self.length = Some(length);
+        self.length_condition = Some(condition);
+        self
+    }
+
+    pub fn hold_off(mut self, hold_off: Duration) -> Self {
+        self.hold_off = Some(hold_off);
+        self
+    }
+
+    /// Position of the trigger relative to the middle of the buffer
+    pub fn position(mut self, position: Duration) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Zero disables the auto trigger
+    pub fn auto_timeout(mut self, timeout: Duration) -> Self {
+        self.auto_timeout = Some(timeout);
+        self
+    }
+
+    fn apply(&self, input: &AnalogIn) -> Result<()> {
+        let handle = input.device.handle;
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInTriggerSourceSet(handle, self.source.code()))?;
+            if let Some(trigger_type) = self.trigger_type {
+                let mut supported: c_int = 0;
+                handle_dwf_errors(FDwfAnalogInTriggerTypeInfo(handle, &mut supported as *mut c_int))?;
+                if !is_bit_set(supported, trigger_type.code()) {
+                    return Err(Error::new(ErrorKind::NotSupported, &format!("{:?} trigger is not available", trigger_type)));
+                }
+                handle_dwf_errors(FDwfAnalogInTriggerTypeSet(handle, trigger_type.code()))?;
+            }
+            if let Some(ix) = self.channel {
+                let (mut min, mut max): (c_int, c_int) = (0, 0);
+                handle_dwf_errors(FDwfAnalogInTriggerChannelInfo(handle, &mut min as *mut c_int, &mut max as *mut c_int))?;
+                if ix < min || ix > max {
+                    return Err(Error::new(ErrorKind::InvalidParameter1,
+                                          &format!("trigger channel #{} is out of [{}, {}] range", ix, min, max)));
+                }
+                handle_dwf_errors(FDwfAnalogInTriggerChannelSet(handle, ix))?;
+            }
+            if let Some(filter) = self.filter {
+                let mut supported: c_int = 0;
+                handle_dwf_errors(FDwfAnalogInTriggerFilterInfo(handle, &mut supported as *mut c_int))?;
+                if !is_bit_set(supported, filter.code()) {
+                    return Err(Error::new(ErrorKind::NotSupported, &format!("{:?} trigger filter is not available", filter)));
+                }
+                handle_dwf_errors(FDwfAnalogInTriggerFilterSet(handle, filter.code()))?;
+            }
+            if let Some(level) = self.level {
+                handle_dwf_errors(FDwfAnalogInTriggerLevelSet(handle, level))?;
+            }
+            if let Some(hysteresis) = self.hysteresis {
+                handle_dwf_errors(FDwfAnalogInTriggerHysteresisSet(handle, hysteresis))?;
+            }
+            if let Some(condition) = self.condition {
+                let mut supported: c_int = 0;
+                handle_dwf_errors(FDwfAnalogInTriggerConditionInfo(handle, &mut supported as *mut c_int))?;
+                if !is_bit_set(supported, condition.code()) {
+                    return Err(Error::new(ErrorKind::NotSupported, &format!("{:?} trigger condition is not available", condition)));
+                }
+                handle_dwf_errors(FDwfAnalogInTriggerConditionSet(handle, condition.code()))?;
+            }
+            if let Some(length) = self.length {
+                handle_dwf_errors(FDwfAnalogInTriggerLengthSet(handle, to_seconds(length)))?;
+            }
+            if let Some(length_condition) = self.length_condition {
+                let mut supported: c_int = 0;
+                handle_dwf_errors(FDwfAnalogInTriggerLengthConditionInfo(handle, &mut supported as *mut c_int))?;
+                if !is_bit_set(supported, length_condition.code()) {
+                    return Err(Error::new(ErrorKind::NotSupported,
+                                          &format!("{:?} trigger length condition is not available", length_condition)));
+                }
+                handle_dwf_errors(FDwfAnalogInTriggerLengthConditionSet(handle, length_condition.code()))?;
+            }
+            if let Some(hold_off) = self.hold_off {
+                handle_dwf_errors(FDwfAnalogInTriggerHoldOffSet(handle, to_seconds(hold_off)))?;
+            }
+            if let Some(position) = self.position {
+                handle_dwf_errors(FDwfAnalogInTriggerPositionSet(handle, to_seconds(position)))?;
+            }
+            if let Some(auto_timeout) = self.auto_timeout {
+                handle_dwf_errors(FDwfAnalogInTriggerAutoTimeoutSet(handle, to_seconds(auto_timeout)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AcquisitionMode {
+    Single,
+    ScanShift,
+    ScanScreen,
+    /// Zero length records until stopped
+    Record { length: Duration },
+    Overs,
+    Single1,
+}
+
+impl AcquisitionMode {
+    fn code(self) -> ACQMODE {
+        match self {
+            AcquisitionMode::Single => acqmodeSingle,
+            AcquisitionMode::ScanShift => acqmodeScanShift,
+            AcquisitionMode::ScanScreen => acqmodeScanScreen,
+            AcquisitionMode::Record { .. } => acqmodeRecord,
+            AcquisitionMode::Overs => acqmodeOvers,
+            AcquisitionMode::Single1 => acqmodeSingle1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SamplingClock {
+    pub source: TriggerSource,
+    pub slope: TriggerSlope,
+    /// Delay between the clock edge and the moment the sample is taken
+    pub delay: Duration,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SampleTiming {
+    /// Evenly spaced samples at `frequency`
+    Internal { frequency: f64 },
+    /// One sample per edge of an external clock
+    External(SamplingClock),
+}
+
+impl SampleTiming {
+    /// Time between consecutive samples, unknown for an external clock
+    pub fn sample_period(&self) -> Option<Duration> {
+        match self {
+            SampleTiming::Internal { frequency } => Some(from_seconds(1.0 / frequency)),
+            SampleTiming::External(_) => None,
+        }
+    }
+
+    /// Offset of every sample relative to its clock
+    pub fn delay(&self) -> Duration {
+        match self {
+            SampleTiming::Internal { .. } => Duration::zero(),
+            SampleTiming::External(clock) => clock.delay,
+        }
+    }
+}
+
+pub struct AnalogIn<'a> {
+    device: &'a Device,
+}
+
+impl<'a> AnalogIn<'a> {
+    pub fn start(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInConfigure(self.device.handle, to_c_bool(false), to_c_bool(true)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_frequency(&self, freq: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInFrequencySet(self.device.handle, freq))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_frequency(&self) -> Result<f64> {
+        unsafe {
+            let mut freq: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInFrequencyGet(self.device.handle, &mut freq as *mut f64))?;
+            Ok(freq)
+        }
+    }
+
+    /// Samples on edges of an external clock, `None` goes back to the internal clock
+    pub fn set_sampling_clock(&self, clock: Option<SamplingClock>) -> Result<()> {
+        unsafe {
+            match clock {
+                Some(clock) => {
+                    handle_dwf_errors(FDwfAnalogInSamplingSourceSet(self.device.handle, clock.source.code()))?;
+                    handle_dwf_errors(FDwfAnalogInSamplingSlopeSet(self.device.handle, clock.slope.code()))?;
+                    handle_dwf_errors(FDwfAnalogInSamplingDelaySet(self.device.handle, to_seconds(clock.delay)))?;
+                },
+                None => {
+                    handle_dwf_errors(FDwfAnalogInSamplingSourceSet(self.device.handle, TriggerSource::NoTrigger.code()))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_sampling_clock(&self) -> Result<Option<SamplingClock>> {
+        unsafe {
+            let mut source: TRIGSRC = 0;
+            handle_dwf_errors(FDwfAnalogInSamplingSourceGet(self.device.handle, &mut source as *mut TRIGSRC))?;
+            let source = TriggerSource::from_code(source)?;
+            if source == TriggerSource::NoTrigger {
+                return Ok(None);
+            }
+            let mut slope: DwfTriggerSlope = 0;
+            handle_dwf_errors(FDwfAnalogInSamplingSlopeGet(self.device.handle, &mut slope as *mut DwfTriggerSlope))?;
+            let mut delay: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInSamplingDelayGet(self.device.handle, &mut delay as *mut f64))?;
+            Ok(Some(SamplingClock {
+                source,
+                slope: TriggerSlope::from_code(slope)?,
+                delay: from_seconds(delay),
+            }))
+        }
+    }
+
+    /// How the samples of the acquisition are spaced in time
+    pub fn get_sample_timing(&self) -> Result<SampleTiming> {
+        Ok(match self.get_sampling_clock()? {
+            Some(clock) => SampleTiming::External(clock),
+            None => SampleTiming::Internal { frequency: self.get_frequency()? },
+        })
+    }
+
+    pub fn set_buffer_size(&self, buf_size: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInBufferSizeSet(self.device.handle, buf_size as i32))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_buffer_size(&self) -> Result<u32> {
+        unsafe {
+            let mut buf_size: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInBufferSizeGet(self.device.handle, &mut buf_size as *mut c_int))?;
+            Ok(buf_size as u32)
+        }
+    }
+
+    /// Resolution of the ADC
+    pub fn get_adc_bits(&self) -> Result<u32> {
+        unsafe {
+            let mut bits: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInBitsInfo(self.device.handle, &mut bits as *mut c_int))?;
+            Ok(bits as u32)
+        }
+    }
+
+    pub fn get_max_noise_size(&self) -> Result<u32> {
+        unsafe {
+            let mut max: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInNoiseSizeInfo(self.device.handle, &mut max as *mut c_int))?;
+            Ok(max as u32)
+        }
+    }
+
+    pub fn set_noise_size(&self, noise_size: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInNoiseSizeSet(self.device.handle, noise_size as c_int))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_noise_size(&self) -> Result<u32> {
+        unsafe {
+            let mut noise_size: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInNoiseSizeGet(self.device.handle, &mut noise_size as *mut c_int))?;
+            Ok(noise_size as u32)
+        }
+    }
+
+    /// Records min/max noise samples along with the data, at the largest noise buffer size available
+    pub fn set_noise_enabled(&self, enabled: bool) -> Result<()> {
+        self.set_noise_size(if enabled { self.get_max_noise_size()? } else { 0 })
+    }
+
+    #[deprecated(note = "use `set_acquisition_mode(AcquisitionMode::Record { length })`")]
+    pub fn set_record_mode(&self, length: f64) -> Result<()> {
+        self.set_acquisition_mode(AcquisitionMode::Record { length: from_seconds(length) })
+    }
+
+    pub fn set_acquisition_mode(&self, mode: AcquisitionMode) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInAcquisitionModeInfo(self.device.handle, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, mode.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("acquisition mode {:?} is not available", mode)));
+            }
+            if let AcquisitionMode::Record { length } = mode {
+                handle_dwf_errors(FDwfAnalogInRecordLengthSet(self.device.handle, to_seconds(length)))?;
+            }
+            handle_dwf_errors(FDwfAnalogInAcquisitionModeSet(self.device.handle, mode.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_acquisition_mode(&self) -> Result<AcquisitionMode> {
+        unsafe {
+            let mut mode: ACQMODE = 0;
+            handle_dwf_errors(FDwfAnalogInAcquisitionModeGet(self.device.handle, &mut mode as *mut ACQMODE))?;
+            Ok(match mode {
+                acqmodeSingle => AcquisitionMode::Single,
+                acqmodeScanShift => AcquisitionMode::ScanShift,
+                acqmodeScanScreen => AcquisitionMode::ScanScreen,
+                acqmodeRecord => {
+                    let mut length: f64 = 0.0;
+                    handle_dwf_errors(FDwfAnalogInRecordLengthGet(self.device.handle, &mut length as *mut f64))?;
+                    AcquisitionMode::Record { length: from_seconds(length) }
+                },
+                acqmodeOvers => AcquisitionMode::Overs,
+                acqmodeSingle1 => AcquisitionMode::Single1,
+                _ => return Err(Error::new(ErrorKind::NotSupported, &format!("unknown acquisition mode #{}", mode))),
+            })
+        }
+    }
+
+    pub fn set_trigger(&self, trigger: &AnalogInTrigger) -> Result<()> {
+        trigger.apply(self)
+    }
+
+    pub fn force_trigger(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInTriggerForce(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    /// Trigger position which was applied to the last acquisition
+    pub fn get_trigger_position_status(&self) -> Result<Duration> {
+        unsafe {
+            let mut position: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInTriggerPositionStatus(self.device.handle, &mut position as *mut f64))?;
+            Ok(from_seconds(position))
+        }
+    }
+
+    /// `true` if the last acquisition was started by the auto trigger timeout
+    pub fn is_auto_triggered(&self) -> Result<bool> {
+        unsafe {
+            let mut auto: BOOL = 0;
+            handle_dwf_errors(FDwfAnalogInStatusAutoTriggered(self.device.handle, &mut auto as *mut BOOL))?;
+            Ok(auto != 0)
+        }
+    }
+
+    pub fn channel_count(&self) -> Result<i32> {
+        unsafe {
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInChannelCount(self.device.handle, &mut cnt as *mut c_int))?;
+            Ok(cnt)
+        }
+    }
+
+    /// Discrete input ranges the channels can be set to
+    pub fn get_range_steps(&self) -> Result<Vec<f64>> {
+        unsafe {
+            let mut steps = [0f64; 32];
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInChannelRangeSteps(self.device.handle, steps.as_mut_ptr(), &mut cnt as *mut c_int))?;
+            Ok(steps[..cnt.max(0).min(32) as usize].to_vec())
+        }
+    }
+
+    pub fn channel(&self, ix: i32) -> AnalogInChannel {
+        AnalogInChannel {
+            input: &self,
+            ix: ix as c_int,
+        }
+    }
+
+    pub fn get_status(&self) -> Result<AnalogAcquisitionStatus> {
+        unsafe {
+            let mut state: DwfState = mem::uninitialized();
+            handle_dwf_errors(FDwfAnalogInStatus(self.device.handle, to_c_bool(true), (&mut state) as *mut DwfState))?;
+            AnalogAcquisitionStatus::from_code(state)
+        }
+    }
+
+    /// Latest ADC reading of every channel, `None` for disabled channels.
+    /// Polls the status without transferring the buffer, the acquisition must be started.
+    pub fn read_voltages(&self) -> Result<Vec<Option<f64>>> {
+        unsafe {
+            let mut state: DwfState = 0;
+            handle_dwf_errors(FDwfAnalogInStatus(self.device.handle, to_c_bool(false), (&mut state) as *mut DwfState))?;
+        }
+        (0..self.channel_count()?)
+            .map(|ix| {
+                let channel = self.channel(ix);
+                if channel.is_enabled()? {
+                    Ok(Some(channel.get_last_sample()?))
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect()
+    }
+
+    /// Waits for `settle`, then averages `count` readings taken `interval` apart, like a multi-channel DMM
+    pub fn read_voltages_averaged(&self, count: u32, interval: Duration, settle: Duration) -> Result<Vec<Option<f64>>> {
+        if count == 0 {
+            return Err(Error::new(ErrorKind::InvalidParameter1, "at least one reading is needed"));
+        }
+        std::thread::sleep(settle.to_std().unwrap_or_default());
+        let mut sums = self.read_voltages()?;
+        for _ in 1..count {
+            std::thread::sleep(interval.to_std().unwrap_or_default());
+            for (sum, value) in sums.iter_mut().zip(self.read_voltages()?) {
+                if let (Some(sum), Some(value)) = (sum.as_mut(), value) {
+                    *sum += value;
+                }
+            }
+        }
+        Ok(sums.into_iter().map(|sum| sum.map(|sum| sum / count as f64)).collect())
+    }
+
+    pub fn get_samples_left(&self) -> Result<i32> {
+        unsafe {
+            let mut ret = mem::uninitialized();
+            handle_dwf_errors(FDwfAnalogInStatusSamplesLeft(self.device.handle, &mut ret as *mut c_int))?;
+            Ok(ret)
+        }
+    }
+
+    pub fn get_samples_valid(&self) -> Result<i32> {
+        unsafe {
+            let mut ret: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInStatusSamplesValid(self.device.handle, &mut ret as *mut c_int))?;
+            Ok(ret)
+        }
+    }
+
+    /// Start in the circular buffer and length of the valid samples, oldest first
+    pub fn get_valid_range(&self) -> Result<(i32, i32)> {
+        let buf_size = self.get_buffer_size()? as i32;
+        let valid = self.get_samples_valid()?.min(buf_size);
+        let write_ix = self.get_write_index()?;
+        Ok(((write_ix - valid).rem_euclid(buf_size.max(1)), valid))
+    }
+
+    /// Position in the circular buffer which the next sample will be written to
+    pub fn get_write_index(&self) -> Result<i32> {
+        unsafe {
+            let mut ret: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInStatusIndexWrite(self.device.handle, &mut ret as *mut c_int))?;
+            Ok(ret)
+        }
+    }
+
+    pub fn get_record_status(&self) -> Result<(i32, i32, i32)> {
+        unsafe {
+            let (mut available, mut lost, mut corrupted) = mem::uninitialized();
+            handle_dwf_errors(FDwfAnalogInStatusRecord(self.device.handle,
+                                                       &mut available as *mut c_int,
+                                                       &mut lost as *mut c_int,
+                                                       &mut corrupted as *mut c_int
+            ))?;
+            Ok((available, lost, corrupted))
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum AnalogAcquisitionStatus {
+    Ready = DwfStateReady as isize,
+    Config = DwfStateConfig as isize,
+    Prefill = DwfStatePrefill as isize,
+    Armed = DwfStateArmed as isize,
+    Waiting = DwfStateWait as isize,
+    Running = DwfStateRunning as isize,
+    Done = DwfStateDone as isize,
+}
+
+impl AnalogAcquisitionStatus {
+    fn from_code(code: DwfState) -> Result<AnalogAcquisitionStatus> {
+        match code {
+            DwfStateReady => Ok(AnalogAcquisitionStatus::Ready),
+            DwfStateConfig => Ok(AnalogAcquisitionStatus::Config),
+            DwfStatePrefill => Ok(AnalogAcquisitionStatus::Prefill),
+            DwfStateArmed => Ok(AnalogAcquisitionStatus::Armed),
+            DwfStateWait => Ok(AnalogAcquisitionStatus::Waiting),
+            DwfStateRunning => Ok(AnalogAcquisitionStatus::Running),
+            DwfStateDone => Ok(AnalogAcquisitionStatus::Done),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown acquisition state #{}", code))),
+        }
+    }
+}
+
+/// Position in a circular scan buffer up to which samples were already fetched
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ScanCursor {
+    write_ix: Option<i32>,
+    valid: i32,
+    last_fetched: (i32, i32),
+}
+
+impl ScanCursor {
+    pub fn new() -> ScanCursor {
+        ScanCursor::default()
+    }
+
+    /// Forgets the position, so the next fetch returns all the valid samples.
+    /// Needed when a new acquisition fills the whole buffer before the next fetch.
+    pub fn reset(&mut self) {
+        *self = ScanCursor::default();
+    }
+
+    /// Moves to `write_ix`, returns the start and the length of the samples written since the last position
+    fn advance(&mut self, write_ix: i32, valid: i32, buf_size: i32) -> (i32, i32) {
+        let valid = valid.min(buf_size);
+        let new = match self.write_ix {
+            // the valid count only drops when a new acquisition starts
+            Some(last_write_ix) if valid >= self.valid => {
+                let written = (write_ix - last_write_ix).rem_euclid(buf_size);
+                if valid < buf_size {
+                    valid - self.valid
+                } else if written < buf_size - self.valid {
+                    // the buffer got full since the last position, so it must have wrapped
+                    written + buf_size
+                } else {
+                    written
+                }
+            },
+            _ => valid,
+        }.min(valid);
+        self.write_ix = Some(write_ix);
+        self.valid = valid;
+        self.last_fetched = ((write_ix - new).rem_euclid(buf_size), new);
+        self.last_fetched
+    }
+
+    /// Start in the circular buffer and length of the samples fetched last time
+    pub fn get_last_fetched(&self) -> (i32, i32) {
+        self.last_fetched
+    }
+}
+
+/// Splits `count` samples starting at `start` of a circular buffer into the part up to the end of the buffer
+/// and the part wrapped around to its beginning, as (start, count) pairs
+fn ring_spans(start: i32, count: i32, buf_size: i32) -> [(i32, i32); 2] {
+    let first = count.min(buf_size - start);
+    [(start, first), (0, count - first)]
+}
+
+/// Conversion of raw ADC samples, which are left-aligned to 16 bits, to volts
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Scaling {
+    /// Volts, peak to peak
+    pub range: f64,
+    /// Volts
+    pub offset: f64,
+    pub adc_bits: u32,
+}
+
+impl Scaling {
+    pub fn to_volts(&self, raw: i16) -> f64 {
+        raw as f64 * self.range / 65536.0 + self.offset
+    }
+
+    pub fn convert(&self, raw: &[i16]) -> Vec<f64> {
+        raw.iter().map(|raw| self.to_volts(*raw)).collect()
+    }
+
+    /// Volts per ADC step
+    pub fn resolution(&self) -> f64 {
+        self.range / (1u64 << self.adc_bits) as f64
+    }
+}
+
+/// Min/max envelope of the signal between data samples, catches glitches narrower than the sample period
+#[derive(Clone, PartialEq, Debug)]
+pub struct NoiseEnvelope {
+    pub min: Vec<f64>,
+    pub max: Vec<f64>,
+    /// Number of data samples covered by each envelope point
+    pub samples_per_point: f64,
+    /// Data sample index which the first envelope point starts at, zero or negative
+    pub offset: f64,
+}
+
+impl NoiseEnvelope {
+    /// Min and max values around the data sample `sample_ix`
+    pub fn at(&self, sample_ix: usize) -> Option<(f64, f64)> {
+        let ix = ((sample_ix as f64 - self.offset) / self.samples_per_point) as usize;
+        Some((*self.min.get(ix)?, *self.max.get(ix)?))
+    }
+}
+
+pub struct AnalogInChannel<'a> {
+    input: &'a AnalogIn<'a>,
+    ix: c_int,
+}
+
+impl<'a> AnalogInChannel<'a> {
+    pub fn set_offset(&self, offset: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInChannelOffsetSet(self.input.device.handle, self.ix, offset))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_offset(&self) -> Result<f64> {
+        unsafe {
+            let mut offset: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInChannelOffsetGet(self.input.device.handle, self.ix, &mut offset as *mut f64))?;
+            Ok(offset)
+        }
+    }
+
+    pub fn set_range(&self, range: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInChannelRangeSet(self.input.device.handle, self.ix, range))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_range(&self) -> Result<f64> {
+        unsafe {
+            let mut range: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInChannelRangeGet(self.input.device.handle, self.ix, &mut range as *mut f64))?;
+            Ok(range)
+        }
+    }
+
+    /// Sets the smallest of the discrete ranges which covers `range`, or the largest one if none does.
+    /// Returns the range which was set.
+    pub fn set_nearest_range(&self, range: f64) -> Result<f64> {
+        let steps = self.input.get_range_steps()?;
+        let covering = steps.iter().cloned().filter(|step| *step >= range).fold(std::f64::NAN, f64::min);
+        let nearest = if covering.is_nan() {
+            steps.iter().cloned().fold(std::f64::NAN, f64::max)
+        } else {
+            covering
+        };
+        if nearest.is_nan() {
+            return Err(Error::new(ErrorKind::NotSupported, "no input ranges are available"));
+        }
+        self.set_range(nearest)?;
+        self.get_range()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInChannelEnableSet(self.input.device.handle, self.ix, to_c_bool(enabled)))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> Result<bool> {
+        unsafe {
+            let mut enabled: BOOL = 0;
+            handle_dwf_errors(FDwfAnalogInChannelEnableGet(self.input.device.handle, self.ix, &mut enabled as *mut BOOL))?;
+            Ok(enabled != 0)
+        }
+    }
+
+    pub fn set_filter(&self, filter: Filter) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfAnalogInChannelFilterInfo(self.input.device.handle, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, filter.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("{:?} filter is not available", filter)));
+            }
+            handle_dwf_errors(FDwfAnalogInChannelFilterSet(self.input.device.handle, self.ix, filter.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_filter(&self) -> Result<Filter> {
+        unsafe {
+            let mut filter: FILTER = 0;
+            handle_dwf_errors(FDwfAnalogInChannelFilterGet(self.input.device.handle, self.ix, &mut filter as *mut FILTER))?;
+            Filter::from_code(filter)
+        }
+    }
+
+    /// Probe attenuation, e.g. 10.0 for a 10x probe
+    pub fn set_attenuation(&self, attenuation: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInChannelAttenuationSet(self.input.device.handle, self.ix, attenuation))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_attenuation(&self) -> Result<f64> {
+        unsafe {
+            let mut attenuation: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInChannelAttenuationGet(self.input.device.handle, self.ix, &mut attenuation as *mut f64))?;
+            Ok(attenuation)
+        }
+    }
+
+    fn fetch_ring(&self, dest: &mut Vec<f64>, start: i32, count: i32, buf_size: i32) -> Result<()> {
+        let mut len = dest.len();
+        dest.resize(len + count as usize, 0.0);
+        for &(start, count) in ring_spans(start, count, buf_size).iter().filter(|&&(_, count)| count > 0) {
+            unsafe {
+                handle_dwf_errors(FDwfAnalogInStatusData2(self.input.device.handle, self.ix,
+                                                          dest.as_mut_ptr().add(len), start, count))?;
+            }
+            len += count as usize;
+        }
+        Ok(())
+    }
+
+    /// Appends all valid samples of a scan mode acquisition to `dest`, oldest first, returns their timing.
+    /// Call `AnalogIn::get_status` first to read the data from the device.
+    pub fn fetch_valid_samples(&self, dest: &mut Vec<f64>) -> Result<SampleTiming> {
+        let buf_size = self.input.get_buffer_size()? as i32;
+        let (start, valid) = self.input.get_valid_range()?;
+        if valid > 0 && buf_size > 0 {
+            self.fetch_ring(dest, start, valid, buf_size)?;
+        }
+        self.input.get_sample_timing()
+    }
+
+    /// Appends samples written since the previous call with the same `cursor` to `dest`, oldest first.
+    /// Samples are lost if more than a buffer's worth is written between the calls. Once the buffer is full,
+    /// exactly a buffer's worth can't be told apart from no new samples, so poll at least twice per buffer.
+    /// A restarted acquisition is detected by its valid sample count, `ScanCursor::reset` covers the rest.
+    pub fn fetch_new_samples(&self, cursor: &mut ScanCursor, dest: &mut Vec<f64>) -> Result<SampleTiming> {
+        let buf_size = self.input.get_buffer_size()? as i32;
+        let valid = self.input.get_samples_valid()?;
+        let write_ix = self.input.get_write_index()?;
+        if buf_size > 0 {
+            let (start, new) = cursor.advance(write_ix, valid, buf_size);
+            if new > 0 {
+                self.fetch_ring(dest, start, new, buf_size)?;
+            }
+        }
+        self.input.get_sample_timing()
+    }
+
+    /// Latest ADC reading as of the last status poll
+    pub fn get_last_sample(&self) -> Result<f64> {
+        unsafe {
+            let mut value: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogInStatusSample(self.input.device.handle, self.ix, &mut value as *mut f64))?;
+            Ok(value)
+        }
+    }
+
+    /// Current scaling of raw ADC samples to volts
+    pub fn get_scaling(&self) -> Result<Scaling> {
+        Ok(Scaling {
+            range: self.get_range()?,
+            offset: self.get_offset()?,
+            adc_bits: self.input.get_adc_bits()?,
+        })
+    }
+
+    /// Appends `available` raw ADC samples to `dest`, returns the scaling to convert them to volts and their timing
+    pub fn fetch_raw_samples(&self, dest: &mut Vec<i16>, available: i32) -> Result<(Scaling, SampleTiming)> {
+        let original_len = dest.len();
+        dest.resize(original_len + available as usize, 0);
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInStatusData16(self.input.device.handle, self.ix,
+                                                       dest.as_mut_ptr().add(original_len), 0, available))?;
+        }
+        Ok((self.get_scaling()?, self.input.get_sample_timing()?))
+    }
+
+    /// Fetches the whole min/max noise buffer of the last single acquisition
+    pub fn fetch_noise(&self) -> Result<NoiseEnvelope> {
+        let noise_size = self.input.get_noise_size()?;
+        if noise_size == 0 {
+            return Err(Error::new(ErrorKind::NotSupported, "noise buffer is disabled"));
+        }
+        let buf_size = self.input.get_buffer_size()?;
+        let mut min = vec![0f64; noise_size as usize];
+        let mut max = vec![0f64; noise_size as usize];
+        unsafe {
+            handle_dwf_errors(FDwfAnalogInStatusNoise(self.input.device.handle, self.ix,
+                                                      min.as_mut_ptr(), max.as_mut_ptr(), noise_size as c_int))?;
+        }
+        Ok(NoiseEnvelope {
+            min,
+            max,
+            samples_per_point: buf_size as f64 / noise_size as f64,
+            offset: 0.0,
+        })
+    }
+
+    /// Fetches the noise envelope around `count` data samples starting at `start` in the circular buffer,
+    /// e.g. those of the last `fetch_new_samples`, see `ScanCursor::get_last_fetched`.
+    /// `NoiseEnvelope::at` takes sample indices relative to `start`.
+    pub fn fetch_noise_range(&self, start: i32, count: i32) -> Result<NoiseEnvelope> {
+        let noise_size = self.input.get_noise_size()? as i32;
+        if noise_size == 0 {
+            return Err(Error::new(ErrorKind::NotSupported, "noise buffer is disabled"));
+        }
+        let samples_per_point = self.input.get_buffer_size()? as f64 / noise_size as f64;
+        let first = (start as f64 / samples_per_point).floor() as i32;
+        let last = ((start + count) as f64 / samples_per_point).ceil() as i32;
+        let points = (last - first).min(noise_size).max(0);
+        let mut min = vec![0f64; points as usize];
+        let mut max = vec![0f64; points as usize];
+        let mut len = 0;
+        for &(start, count) in ring_spans(first.rem_euclid(noise_size), points, noise_size).iter().filter(|&&(_, count)| count > 0) {
+            unsafe {
+                handle_dwf_errors(FDwfAnalogInStatusNoise2(self.input.device.handle, self.ix,
+                                                           min.as_mut_ptr().add(len), max.as_mut_ptr().add(len), start, count))?;
+            }
+            len += count as usize;
+        }
+        Ok(NoiseEnvelope {
+            min,
+            max,
+            samples_per_point,
+            offset: first as f64 * samples_per_point - start as f64,
+        })
+    }
+
+    /// Appends `available` samples to `dest`, returns their timing
+    pub fn fetch_samples(&self, dest: &mut Vec<f64>, available: i32) -> Result<SampleTiming> {
+        unsafe {
+            let original_len = dest.len();
+            dest.reserve(available as usize);
+            dest.set_len(original_len + available as usize);
+            handle_dwf_errors(FDwfAnalogInStatusData(self.input.device.handle, self.ix,
+                                                     dest.as_mut_ptr().offset(original_len as isize), available))?;
+        }
+        self.input.get_sample_timing()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DigitalIOCapabilities {
+    /// Lines which can be switched to output
+    pub output_enable: u64,
+    /// Lines whose output value can be set
+    pub output: u64,
+    /// Lines which can be read
+    pub input: u64,
+}
+
+impl DigitalIOCapabilities {
+    pub fn line_count(&self) -> u32 {
+        64 - (self.output_enable | self.output | self.input).leading_zeros()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DigitalIODirection {
+    Input,
+    Output,
+}
+
+pub struct DigitalIO<'a> {
+    device: &'a Device,
+    wide: bool,
+}
+
+impl<'a> DigitalIO<'a> {
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalIOReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn configure(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalIOConfigure(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn capabilities(&self) -> Result<DigitalIOCapabilities> {
+        unsafe {
+            let mut output_enable: u64 = 0;
+            let mut output: u64 = 0;
+            let mut input: u64 = 0;
+            handle_dwf_errors(FDwfDigitalIOOutputEnableInfo64(self.device.handle, &mut output_enable as *mut u64))?;
+            handle_dwf_errors(FDwfDigitalIOOutputInfo64(self.device.handle, &mut output as *mut u64))?;
+            handle_dwf_errors(FDwfDigitalIOInputInfo64(self.device.handle, &mut input as *mut u64))?;
+            Ok(DigitalIOCapabilities { output_enable, output, input })
+        }
+    }
+
+    /// `true` if the device has more than 32 lines and the 64-bit calls are used
+    pub fn is_wide(&self) -> bool {
+        self.wide
+    }
+
+    fn check_mask(&self, mask: u64) -> Result<()> {
+        if !self.wide && mask >> 32 != 0 {
+            return Err(Error::new(ErrorKind::InvalidParameter1, "device has only 32 digital I/O lines"));
+        }
+        Ok(())
+    }
+
+    pub fn set_output_enable(&self, mask: u64) -> Result<()> {
+        self.check_mask(mask)?;
+        unsafe {
+            if self.wide {
+                handle_dwf_errors(FDwfDigitalIOOutputEnableSet64(self.device.handle, mask))?;
+            } else {
+                handle_dwf_errors(FDwfDigitalIOOutputEnableSet(self.device.handle, mask as u32))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_output_enable(&self) -> Result<u64> {
+        unsafe {
+            if self.wide {
+                let mut mask: u64 = 0;
+                handle_dwf_errors(FDwfDigitalIOOutputEnableGet64(self.device.handle, &mut mask as *mut u64))?;
+                Ok(mask)
+            } else {
+                let mut mask: u32 = 0;
+                handle_dwf_errors(FDwfDigitalIOOutputEnableGet(self.device.handle, &mut mask as *mut u32))?;
+                Ok(mask as u64)
+            }
+        }
+    }
+
+    pub fn set_output(&self, value: u64) -> Result<()> {
+        self.check_mask(value)?;
+        unsafe {
+            if self.wide {
+                handle_dwf_errors(FDwfDigitalIOOutputSet64(self.device.handle, value))?;
+            } else {
+                handle_dwf_errors(FDwfDigitalIOOutputSet(self.device.handle, value as u32))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_output(&self) -> Result<u64> {
+        unsafe {
+            if self.wide {
+                let mut value: u64 = 0;
+                handle_dwf_errors(FDwfDigitalIOOutputGet64(self.device.handle, &mut value as *mut u64))?;
+                Ok(value)
+            } else {
+                let mut value: u32 = 0;
+                handle_dwf_errors(FDwfDigitalIOOutputGet(self.device.handle, &mut value as *mut u32))?;
+                Ok(value as u64)
+            }
+        }
+    }
+
+    /// Reads the current state of all input lines
+    pub fn read_input(&self) -> Result<u64> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalIOStatus(self.device.handle))?;
+            if self.wide {
+                let mut value: u64 = 0;
+                handle_dwf_errors(FDwfDigitalIOInputStatus64(self.device.handle, &mut value as *mut u64))?;
+                Ok(value)
+            } else {
+                let mut value: u32 = 0;
+                handle_dwf_errors(FDwfDigitalIOInputStatus(self.device.handle, &mut value as *mut u32))?;
+                Ok(value as u64)
+            }
+        }
+    }
+
+    pub fn pin(&self, ix: u32) -> DigitalIOPin {
+        DigitalIOPin {
+            io: &self,
+            ix,
+        }
+    }
+}
+
+pub struct DigitalIOPin<'a> {
+    io: &'a DigitalIO<'a>,
+    ix: u32,
+}
+
+impl<'a> DigitalIOPin<'a> {
+    fn mask(&self) -> Result<u64> {
+        if self.ix >= if self.io.wide { 64 } else { 32 } {
+            return Err(Error::new(ErrorKind::InvalidParameter1, &format!("there's no digital I/O line #{}", self.ix)));
+        }
+        Ok(1u64 << self.ix)
+    }
+
+    pub fn set_direction(&self, direction: DigitalIODirection) -> Result<()> {
+        let mask = self.mask()?;
+        let enabled = self.io.get_output_enable()?;
+        match direction {
+            DigitalIODirection::Input => self.io.set_output_enable(enabled & !mask),
+            DigitalIODirection::Output => {
+                if self.io.capabilities()?.output_enable & mask == 0 {
+                    return Err(Error::new(ErrorKind::NotSupported, &format!("digital I/O line #{} can't be an output", self.ix)));
+                }
+                self.io.set_output_enable(enabled | mask)
+            },
+        }
+    }
+
+    pub fn get_direction(&self) -> Result<DigitalIODirection> {
+        let mask = self.mask()?;
+        Ok(if self.io.get_output_enable()? & mask != 0 {
+            DigitalIODirection::Output
+        } else {
+            DigitalIODirection::Input
+        })
+    }
+
+    /// Sets the level driven on the line while it's an output
+    pub fn set_output(&self, high: bool) -> Result<()> {
+        let mask = self.mask()?;
+        let value = self.io.get_output()?;
+        self.io.set_output(if high { value | mask } else { value & !mask })
+    }
+
+    pub fn get_output(&self) -> Result<bool> {
+        let mask = self.mask()?;
+        Ok(self.io.get_output()? & mask != 0)
+    }
+
+    pub fn read(&self) -> Result<bool> {
+        let mask = self.mask()?;
+        Ok(self.io.read_input()? & mask != 0)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum DigitalInClockSource {
+    Internal = DwfDigitalInClockSourceInternal as isize,
+    External = DwfDigitalInClockSourceExternal as isize,
+}
+
+impl DigitalInClockSource {
+    fn code(self) -> DwfDigitalInClockSource {
+        self as DwfDigitalInClockSource
+    }
+
+    fn from_code(code: DwfDigitalInClockSource) -> Result<DigitalInClockSource> {
+        match code {
+            DwfDigitalInClockSourceInternal => Ok(DigitalInClockSource::Internal),
+            DwfDigitalInClockSourceExternal => Ok(DigitalInClockSource::External),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown clock source #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum DigitalSampleFormat {
+    Bits8 = 8,
+    Bits16 = 16,
+    Bits32 = 32,
+}
+
+impl DigitalSampleFormat {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+
+    fn from_code(code: c_int) -> Result<DigitalSampleFormat> {
+        match code {
+            8 => Ok(DigitalSampleFormat::Bits8),
+            16 => Ok(DigitalSampleFormat::Bits16),
+            32 => Ok(DigitalSampleFormat::Bits32),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown sample format of {} bits", code))),
+        }
+    }
+}
+
+/// Word type which a logic analyzer sample can be fetched into
+pub trait DigitalSample: Copy + Default {
+    const FORMAT: DigitalSampleFormat;
+}
+
+impl DigitalSample for u8 {
+    const FORMAT: DigitalSampleFormat = DigitalSampleFormat::Bits8;
+}
+
+impl DigitalSample for u16 {
+    const FORMAT: DigitalSampleFormat = DigitalSampleFormat::Bits16;
+}
+
+impl DigitalSample for u32 {
+    const FORMAT: DigitalSampleFormat = DigitalSampleFormat::Bits32;
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum DigitalAcquisitionMode {
+    Single = acqmodeSingle as isize,
+    ScanShift = acqmodeScanShift as isize,
+    ScanScreen = acqmodeScanScreen as isize,
+    Record = acqmodeRecord as isize,
+}
+
+impl DigitalAcquisitionMode {
+    fn code(self) -> ACQMODE {
+        self as ACQMODE
+    }
+
+    fn from_code(code: ACQMODE) -> Result<DigitalAcquisitionMode> {
+        match code {
+            acqmodeSingle => Ok(DigitalAcquisitionMode::Single),
+            acqmodeScanShift => Ok(DigitalAcquisitionMode::ScanShift),
+            acqmodeScanScreen => Ok(DigitalAcquisitionMode::ScanScreen),
+            acqmodeRecord => Ok(DigitalAcquisitionMode::Record),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown acquisition mode #{}", code))),
+        }
+    }
+}
+
+pub struct DigitalIn<'a> {
+    device: &'a Device,
+}
+
+impl<'a> DigitalIn<'a> {
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn configure(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInConfigure(self.device.handle, to_c_bool(true), to_c_bool(false)))?;
+        }
+        Ok(())
+    }
+
+    pub fn start(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInConfigure(self.device.handle, to_c_bool(false), to_c_bool(true)))?;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInConfigure(self.device.handle, to_c_bool(false), to_c_bool(false)))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_internal_clock_frequency(&self) -> Result<f64> {
+        unsafe {
+            let mut freq: f64 = 0.0;
+            handle_dwf_errors(FDwfDigitalInInternalClockInfo(self.device.handle, &mut freq as *mut f64))?;
+            Ok(freq)
+        }
+    }
+
+    pub fn set_clock_source(&self, src: DigitalInClockSource) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInClockSourceInfo(self.device.handle, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, src.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("clock source {:?} is not available", src)));
+            }
+            handle_dwf_errors(FDwfDigitalInClockSourceSet(self.device.handle, src.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_clock_source(&self) -> Result<DigitalInClockSource> {
+        unsafe {
+            let mut src: DwfDigitalInClockSource = 0;
+            handle_dwf_errors(FDwfDigitalInClockSourceGet(self.device.handle, &mut src as *mut DwfDigitalInClockSource))?;
+            DigitalInClockSource::from_code(src)
+        }
+    }
+
+    pub fn get_max_divider(&self) -> Result<u32> {
+        unsafe {
+            let mut max: u32 = 0;
+            handle_dwf_errors(FDwfDigitalInDividerInfo(self.device.handle, &mut max as *mut u32))?;
+            Ok(max)
+        }
+    }
+
+    pub fn set_divider(&self, divider: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInDividerSet(self.device.handle, divider))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_divider(&self) -> Result<u32> {
+        unsafe {
+            let mut divider: u32 = 0;
+            handle_dwf_errors(FDwfDigitalInDividerGet(self.device.handle, &mut divider as *mut u32))?;
+            Ok(divider)
+        }
+    }
+
+    /// Picks the divider closest to the requested sample rate and returns the resulting rate.
+    /// Only meaningful with the internal clock source.
+    pub fn set_sample_rate(&self, freq: f64) -> Result<f64> {
+        let clock = self.get_internal_clock_frequency()?;
+        let divider = (clock / freq).round().max(1.0).min(self.get_max_divider()? as f64) as u32;
+        self.set_divider(divider)?;
+        self.get_sample_rate()
+    }
+
+    /// Sample rate produced by the internal clock and the current divider
+    pub fn get_sample_rate(&self) -> Result<f64> {
+        Ok(self.get_internal_clock_frequency()? / self.get_divider()?.max(1) as f64)
+    }
+
+    /// Number of lines the logic analyzer can sample
+    pub fn get_bits(&self) -> Result<u32> {
+        unsafe {
+            let mut bits: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInBitsInfo(self.device.handle, &mut bits as *mut c_int))?;
+            Ok(bits as u32)
+        }
+    }
+
+    pub fn set_sample_format(&self, format: DigitalSampleFormat) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInSampleFormatSet(self.device.handle, format.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_sample_format(&self) -> Result<DigitalSampleFormat> {
+        unsafe {
+            let mut bits: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInSampleFormatGet(self.device.handle, &mut bits as *mut c_int))?;
+            DigitalSampleFormat::from_code(bits)
+        }
+    }
+
+    pub fn get_max_buffer_size(&self) -> Result<u32> {
+        unsafe {
+            let mut max: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInBufferSizeInfo(self.device.handle, &mut max as *mut c_int))?;
+            Ok(max as u32)
+        }
+    }
+
+    pub fn set_buffer_size(&self, buf_size: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInBufferSizeSet(self.device.handle, buf_size as c_int))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_buffer_size(&self) -> Result<u32> {
+        unsafe {
+            let mut buf_size: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInBufferSizeGet(self.device.handle, &mut buf_size as *mut c_int))?;
+            Ok(buf_size as u32)
+        }
+    }
+
+    pub fn set_acquisition_mode(&self, mode: DigitalAcquisitionMode) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInAcquisitionModeInfo(self.device.handle, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, mode.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("acquisition mode {:?} is not available", mode)));
+            }
+            handle_dwf_errors(FDwfDigitalInAcquisitionModeSet(self.device.handle, mode.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_acquisition_mode(&self) -> Result<DigitalAcquisitionMode> {
+        unsafe {
+            let mut mode: ACQMODE = 0;
+            handle_dwf_errors(FDwfDigitalInAcquisitionModeGet(self.device.handle, &mut mode as *mut ACQMODE))?;
+            DigitalAcquisitionMode::from_code(mode)
+        }
+    }
+
+    pub fn get_status(&self) -> Result<DigitalAcquisitionStatus> {
+        unsafe {
+            let mut state: DwfState = 0;
+            handle_dwf_errors(FDwfDigitalInStatus(self.device.handle, to_c_bool(true), (&mut state) as *mut DwfState))?;
+            DigitalAcquisitionStatus::from_code(state)
+        }
+    }
+
+    pub fn get_samples_left(&self) -> Result<i32> {
+        unsafe {
+            let mut ret: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInStatusSamplesLeft(self.device.handle, &mut ret as *mut c_int))?;
+            Ok(ret)
+        }
+    }
+
+    pub fn get_samples_valid(&self) -> Result<i32> {
+        unsafe {
+            let mut ret: c_int = 0;
+            handle_dwf_errors(FDwfDigitalInStatusSamplesValid(self.device.handle, &mut ret as *mut c_int))?;
+            Ok(ret)
+        }
+    }
+
+    pub fn get_record_status(&self) -> Result<(i32, i32, i32)> {
+        unsafe {
+            let (mut available, mut lost, mut corrupted): (c_int, c_int, c_int) = (0, 0, 0);
+            handle_dwf_errors(FDwfDigitalInStatusRecord(self.device.handle,
+                                                        &mut available as *mut c_int,
+                                                        &mut lost as *mut c_int,
+                                                        &mut corrupted as *mut c_int
+            ))?;
+            Ok((available, lost, corrupted))
+        }
+    }
+
+    /// Appends `available` samples to `dest`; the word type must match the configured sample format
+    pub fn fetch_samples<T: DigitalSample>(&self, dest: &mut Vec<T>, available: i32) -> Result<()> {
+        let format = self.get_sample_format()?;
+        if format != T::FORMAT {
+            return Err(Error::new(ErrorKind::InvalidParameter1,
+                                  &format!("sample format is {:?}, can't fetch {:?} samples", format, T::FORMAT)));
+        }
+        let original_len = dest.len();
+        dest.resize(original_len + available as usize, T::default());
+        unsafe {
+            handle_dwf_errors(FDwfDigitalInStatusData(self.device.handle,
+                                                      dest.as_mut_ptr().add(original_len) as *mut c_void,
+                                                      available * mem::size_of::<T>() as c_int))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum DigitalAcquisitionStatus {
+    Ready = DwfStateReady as isize,
+    Config = DwfStateConfig as isize,
+    Prefill = DwfStatePrefill as isize,
+    Armed = DwfStateArmed as isize,
+    Waiting = DwfStateWait as isize,
+    Running = DwfStateRunning as isize,
+    Done = DwfStateDone as isize,
+}
+
+impl DigitalAcquisitionStatus {
+    fn from_code(code: DwfState) -> Result<DigitalAcquisitionStatus> {
+        match code {
+            DwfStateReady => Ok(DigitalAcquisitionStatus::Ready),
+            DwfStateConfig => Ok(DigitalAcquisitionStatus::Config),
+            DwfStatePrefill => Ok(DigitalAcquisitionStatus::Prefill),
+            DwfStateArmed => Ok(DigitalAcquisitionStatus::Armed),
+            DwfStateWait => Ok(DigitalAcquisitionStatus::Waiting),
+            DwfStateRunning => Ok(DigitalAcquisitionStatus::Running),
+            DwfStateDone => Ok(DigitalAcquisitionStatus::Done),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown acquisition state #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum DigitalOutDrive {
+    PushPull = DwfDigitalOutOutputPushPull as isize,
+    OpenDrain = DwfDigitalOutOutputOpenDrain as isize,
+    OpenSource = DwfDigitalOutOutputOpenSource as isize,
+    /// Only for custom and random outputs
+    ThreeState = DwfDigitalOutOutputThreeState as isize,
+}
+
+impl DigitalOutDrive {
+    fn code(self) -> DwfDigitalOutOutput {
+        self as DwfDigitalOutOutput
+    }
+
+    fn from_code(code: DwfDigitalOutOutput) -> Result<DigitalOutDrive> {
+        match code {
+            DwfDigitalOutOutputPushPull => Ok(DigitalOutDrive::PushPull),
+            DwfDigitalOutOutputOpenDrain => Ok(DigitalOutDrive::OpenDrain),
+            DwfDigitalOutOutputOpenSource => Ok(DigitalOutDrive::OpenSource),
+            DwfDigitalOutOutputThreeState => Ok(DigitalOutDrive::ThreeState),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown output drive #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum DigitalOutIdleMode {
+    Initial = DwfDigitalOutIdleInit as isize,
+    Low = DwfDigitalOutIdleLow as isize,
+    High = DwfDigitalOutIdleHigh as isize,
+    HighImpedance = DwfDigitalOutIdleZet as isize,
+}
+
+impl DigitalOutIdleMode {
+    fn code(self) -> DwfDigitalOutIdle {
+        self as DwfDigitalOutIdle
+    }
+
+    fn from_code(code: DwfDigitalOutIdle) -> Result<DigitalOutIdleMode> {
+        match code {
+            DwfDigitalOutIdleInit => Ok(DigitalOutIdleMode::Initial),
+            DwfDigitalOutIdleLow => Ok(DigitalOutIdleMode::Low),
+            DwfDigitalOutIdleHigh => Ok(DigitalOutIdleMode::High),
+            DwfDigitalOutIdleZet => Ok(DigitalOutIdleMode::HighImpedance),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown idle mode #{}", code))),
+        }
+    }
+}
+
+pub enum DigitalOutFunction {
+    /// `low` and `high` are the number of divided clock ticks spent in each state
+    Pulse { divider: u32, low: u32, high: u32, initial_high: bool },
+    /// Bits are played back one per divided clock tick
+    Custom { divider: u32, bits: Vec<bool> },
+    Random { divider: u32 },
+}
+
+pub struct DigitalOutChannel<'a> {
+    out: &'a DigitalOut<'a>,
+    ix: c_int,
+}
+
+impl<'a> DigitalOutChannel<'a> {
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutEnableSet(self.out.device.handle, self.ix, to_c_bool(enabled)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_function(&self, func: DigitalOutFunction) -> Result<()> {
+        unsafe {
+            match func {
+                DigitalOutFunction::Pulse { divider, low, high, initial_high } => {
+                    self.check_type(DwfDigitalOutTypePulse)?;
+                    self.check_counter(low)?;
+                    self.check_counter(high)?;
+                    handle_dwf_errors(FDwfDigitalOutTypeSet(self.out.device.handle, self.ix, DwfDigitalOutTypePulse))?;
+                    self.set_divider(divider)?;
+                    handle_dwf_errors(FDwfDigitalOutCounterSet(self.out.device.handle, self.ix, low, high))?;
+                    handle_dwf_errors(FDwfDigitalOutCounterInitSet(self.out.device.handle, self.ix, to_c_bool(initial_high), 0))?;
+                },
+                DigitalOutFunction::Custom { divider, bits } => {
+                    self.check_type(DwfDigitalOutTypeCustom)?;
+                    let mut max_bits: u32 = 0;
+                    handle_dwf_errors(FDwfDigitalOutDataInfo(self.out.device.handle, self.ix, &mut max_bits as *mut u32))?;
+                    if bits.len() > max_bits as usize {
+                        return Err(Error::new(ErrorKind::InvalidParameter2,
+                                              &format!("{} bits don't fit in the {} bit buffer", bits.len(), max_bits)));
+                    }
+                    let mut data = vec![0u8; (bits.len() + 7) / 8];
+                    for (ix, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+                        data[ix / 8] |= 1 << (ix % 8);
+                    }
+                    handle_dwf_errors(FDwfDigitalOutTypeSet(self.out.device.handle, self.ix, DwfDigitalOutTypeCustom))?;
+                    self.set_divider(divider)?;
+                    handle_dwf_errors(FDwfDigitalOutDataSet(self.out.device.handle, self.ix,
+                                                            data.as_mut_ptr() as *mut c_void, bits.len() as u32))?;
+                },
+                DigitalOutFunction::Random { divider } => {
+                    self.check_type(DwfDigitalOutTypeRandom)?;
+                    handle_dwf_errors(FDwfDigitalOutTypeSet(self.out.device.handle, self.ix, DwfDigitalOutTypeRandom))?;
+                    self.set_divider(divider)?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn check_type(&self, ty: DwfDigitalOutType) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfDigitalOutTypeInfo(self.out.device.handle, self.ix, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, ty) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("channel #{} doesn't support this output type", self.ix)));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_counter(&self, counter: u32) -> Result<()> {
+        unsafe {
+            let (mut min, mut max): (u32, u32) = (0, 0);
+            handle_dwf_errors(FDwfDigitalOutCounterInfo(self.out.device.handle, self.ix, &mut min as *mut u32, &mut max as *mut u32))?;
+            if counter < min || counter > max {
+                return Err(Error::new(ErrorKind::InvalidParameter2,
+                                      &format!("counter value {} is out of [{}, {}] range", counter, min, max)));
+            }
+        }
+        Ok(())
+    }
+
+    fn set_divider(&self, divider: u32) -> Result<()> {
+        unsafe {
+            let (mut min, mut max): (u32, u32) = (0, 0);
+            handle_dwf_errors(FDwfDigitalOutDividerInfo(self.out.device.handle, self.ix, &mut min as *mut u32, &mut max as *mut u32))?;
+            if divider < min || divider > max {
+                return Err(Error::new(ErrorKind::InvalidParameter2,
+                                      &format!("divider {} is out of [{}, {}] range", divider, min, max)));
+            }
+            handle_dwf_errors(FDwfDigitalOutDividerInitSet(self.out.device.handle, self.ix, 0))?;
+            handle_dwf_errors(FDwfDigitalOutDividerSet(self.out.device.handle, self.ix, divider))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_drive(&self, drive: DigitalOutDrive) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfDigitalOutOutputInfo(self.out.device.handle, self.ix, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, drive.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("channel #{} doesn't support {:?} drive", self.ix, drive)));
+            }
+            handle_dwf_errors(FDwfDigitalOutOutputSet(self.out.device.handle, self.ix, drive.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_drive(&self) -> Result<DigitalOutDrive> {
+        unsafe {
+            let mut drive: DwfDigitalOutOutput = 0;
+            handle_dwf_errors(FDwfDigitalOutOutputGet(self.out.device.handle, self.ix, &mut drive as *mut DwfDigitalOutOutput))?;
+            DigitalOutDrive::from_code(drive)
+        }
+    }
+
+    pub fn set_idle_mode(&self, mode: DigitalOutIdleMode) -> Result<()> {
+        unsafe {
+            let mut supported: c_int = 0;
+            handle_dwf_errors(FDwfDigitalOutIdleInfo(self.out.device.handle, self.ix, &mut supported as *mut c_int))?;
+            if !is_bit_set(supported, mode.code()) {
+                return Err(Error::new(ErrorKind::NotSupported, &format!("channel #{} doesn't support {:?} idle mode", self.ix, mode)));
+            }
+            handle_dwf_errors(FDwfDigitalOutIdleSet(self.out.device.handle, self.ix, mode.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_idle_mode(&self) -> Result<DigitalOutIdleMode> {
+        unsafe {
+            let mut mode: DwfDigitalOutIdle = 0;
+            handle_dwf_errors(FDwfDigitalOutIdleGet(self.out.device.handle, self.ix, &mut mode as *mut DwfDigitalOutIdle))?;
+            DigitalOutIdleMode::from_code(mode)
+        }
+    }
+}
+
+pub struct DigitalOut<'a> {
+    device: &'a Device,
+}
+
+impl<'a> DigitalOut<'a> {
+    pub fn channel_count(&self) -> Result<u32> {
+        unsafe {
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfDigitalOutCount(self.device.handle, &mut cnt as *mut c_int))?;
+            Ok(cnt as u32)
+        }
+    }
+
+    pub fn channel(&self, ix: u32) -> DigitalOutChannel {
+        DigitalOutChannel {
+            out: &self,
+            ix: ix as c_int,
+        }
+    }
+
+    pub fn get_internal_clock_frequency(&self) -> Result<f64> {
+        unsafe {
+            let mut freq: f64 = 0.0;
+            handle_dwf_errors(FDwfDigitalOutInternalClockInfo(self.device.handle, &mut freq as *mut f64))?;
+            Ok(freq)
+        }
+    }
+
+    pub fn set_duration(&self, duration: Duration) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutRunSet(self.device.handle, to_seconds(duration)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_wait(&self, wait: Duration) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutWaitSet(self.device.handle, to_seconds(wait)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_repeat_count(&self, repeat_cnt: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutRepeatSet(self.device.handle, repeat_cnt))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_repeat_on_trigger(&self, enabled: bool) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutRepeatTriggerSet(self.device.handle, to_c_bool(enabled)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_trigger_source(&self, src: TriggerSource) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutTriggerSourceSet(self.device.handle, src.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_trigger_slope(&self, slope: TriggerSlope) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutTriggerSlopeSet(self.device.handle, slope.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn start(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutConfigure(self.device.handle, to_c_bool(true)))?;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalOutConfigure(self.device.handle, to_c_bool(false)))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_status(&self) -> Result<GeneratorStatus> {
+        unsafe {
+            let mut state: DwfState = 0;
+            handle_dwf_errors(FDwfDigitalOutStatus(self.device.handle, (&mut state) as *mut DwfState))?;
+            GeneratorStatus::from_code(state)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum Parity {
+    None = 0,
+    Odd = 1,
+    Even = 2,
+}
+
+impl Parity {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct UartConfig {
+    pub baud_rate: f64,
+    pub data_bits: u32,
+    pub parity: Parity,
+    pub stop_bits: f64,
+    /// Digital I/O line used for transmission
+    pub tx: u32,
+    /// Digital I/O line used for reception
+    pub rx: u32,
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        UartConfig {
+            baud_rate: 9600.0,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1.0,
+            tx: 0,
+            rx: 1,
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum UartError {
+    Dwf(Error),
+    /// Bytes were received, but at least one of them failed the parity check.
+    /// `position` is the 1-based index of the first such byte.
+    Parity { received: usize, position: i32 },
+    /// The receive buffer overflowed and some bytes were lost before the `received` ones
+    Overflow { received: usize },
+}
+
+impl Display for UartError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            UartError::Dwf(e) => e.fmt(f),
+            UartError::Parity { received, position } =>
+                write!(f, "Parity error at position {} among {} received bytes", position, received),
+            UartError::Overflow { received } =>
+                write!(f, "Receive buffer overflow, bytes were lost before {} received bytes", received),
+        }
+    }
+}
+
+impl From<Error> for UartError {
+    fn from(e: Error) -> Self {
+        UartError::Dwf(e)
+    }
+}
+
+pub struct Uart<'a> {
+    device: &'a Device,
+    read_timeout: Option<Duration>,
+    /// Reported by the next `std::io::Read::read`, after the bytes received before it
+    pending_error: Option<UartError>,
+}
+
+impl<'a> Uart<'a> {
+    fn configure(&self, config: &UartConfig) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalUartReset(self.device.handle))?;
+            handle_dwf_errors(FDwfDigitalUartRateSet(self.device.handle, config.baud_rate))?;
+            handle_dwf_errors(FDwfDigitalUartBitsSet(self.device.handle, config.data_bits as c_int))?;
+            handle_dwf_errors(FDwfDigitalUartParitySet(self.device.handle, config.parity.code()))?;
+            handle_dwf_errors(FDwfDigitalUartStopSet(self.device.handle, config.stop_bits))?;
+            handle_dwf_errors(FDwfDigitalUartTxSet(self.device.handle, config.tx as c_int))?;
+            handle_dwf_errors(FDwfDigitalUartRxSet(self.device.handle, config.rx as c_int))?;
+
+            // zero-length transfers start driving the idle level and start the reception
+            handle_dwf_errors(FDwfDigitalUartTx(self.device.handle, std::ptr::null_mut(), 0))?;
+            let (mut received, mut parity): (c_int, c_int) = (0, 0);
+            handle_dwf_errors(FDwfDigitalUartRx(self.device.handle, std::ptr::null_mut(), 0,
+                                                &mut received as *mut c_int, &mut parity as *mut c_int))?;
+        }
+        Ok(())
+    }
+
+    /// Timeout for blocking reads through `std::io::Read`, `None` blocks until data arrives
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalUartTx(self.device.handle, data.as_ptr() as *mut c_char, data.len() as c_int))?;
+        }
+        Ok(())
+    }
+
+    /// Reads whatever has been received so far, without blocking
+    pub fn read(&self, buf: &mut [u8]) -> std::result::Result<usize, UartError> {
+        let (mut received, mut parity): (c_int, c_int) = (0, 0);
+        unsafe {
+            handle_dwf_errors(FDwfDigitalUartRx(self.device.handle, buf.as_mut_ptr() as *mut c_char, buf.len() as c_int,
+                                                &mut received as *mut c_int, &mut parity as *mut c_int))?;
+        }
+        if parity < 0 {
+            return Err(UartError::Overflow { received: received as usize });
+        }
+        if parity > 0 {
+            return Err(UartError::Parity { received: received as usize, position: parity });
+        }
+        Ok(received as usize)
+    }
+}
+
+fn to_io_error(e: UartError) -> std::io::Error {
+    match e {
+        UartError::Parity { .. } | UartError::Overflow { .. } => std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+        UartError::Dwf(_) => std::io::Error::new(std::io::ErrorKind::Other, e.to_string()),
+    }
+}
+
+/// Bytes received before a parity error or after an overflow are returned first and the error is reported
+/// by the next call. Bytes at and after a parity error are dropped.
+impl<'a> std::io::Read for Uart<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if let Some(e) = self.pending_error.take() {
+            return Err(to_io_error(e));
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let started = time::PreciseTime::now();
+        loop {
+            match Uart::read(self, buf) {
+                Ok(0) => {},
+                Ok(received) => return Ok(received),
+                Err(e) => {
+                    let good = match e {
+                        UartError::Parity { received, position } => received.min((position - 1).max(0) as usize),
+                        UartError::Overflow { received } => received,
+                        UartError::Dwf(_) => 0,
+                    };
+                    if good == 0 {
+                        return Err(to_io_error(e));
+                    }
+                    self.pending_error = Some(e);
+                    return Ok(good);
+                },
+            }
+            if let Some(timeout) = self.read_timeout {
+                if started.to(time::PreciseTime::now()) >= timeout {
+                    return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "UART read timed out"));
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+}
+
+impl<'a> std::io::Write for Uart<'a> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Uart::write(self, buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Clock polarity and phase
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum SpiMode {
+    /// CPOL = 0, CPHA = 0
+    Mode0 = 0,
+    /// CPOL = 0, CPHA = 1
+    Mode1 = 1,
+    /// CPOL = 1, CPHA = 0
+    Mode2 = 2,
+    /// CPOL = 1, CPHA = 1
+    Mode3 = 3,
+}
+
+impl SpiMode {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum BitOrder {
+    MsbFirst = 0,
+    LsbFirst = 1,
+}
+
+impl BitOrder {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+}
+
+/// Number of data lines used for a transfer
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum SpiBusWidth {
+    /// Half-duplex over DQ0
+    Siso = 0,
+    /// Full-duplex, DQ0 is MOSI and DQ1 is MISO
+    MosiMiso = 1,
+    Dual = 2,
+    Quad = 4,
+}
+
+impl SpiBusWidth {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ChipSelect {
+    Low,
+    High,
+    HighImpedance,
+}
+
+impl ChipSelect {
+    fn code(self) -> c_int {
+        match self {
+            ChipSelect::Low => 0,
+            ChipSelect::High => 1,
+            ChipSelect::HighImpedance => -1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpiConfig {
+    pub frequency: f64,
+    /// Digital I/O line used for the clock
+    pub clock: u32,
+    /// Digital I/O lines used for DQ0 (MOSI/SISO), DQ1 (MISO), DQ2 and DQ3
+    pub dq: [Option<u32>; 4],
+    pub mode: SpiMode,
+    pub bit_order: BitOrder,
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        SpiConfig {
+            frequency: 1e6,
+            clock: 0,
+            dq: [Some(1), Some(2), None, None],
+            mode: SpiMode::Mode0,
+            bit_order: BitOrder::MsbFirst,
+        }
+    }
+}
+
+mod spi_word {
+    use super::*;
+
+    /// Keeps `SpiWord` implementable only for the word types the DLL supports
+    pub trait Sealed {
+        unsafe fn write_read(hdwf: HDWF, dq: c_int, bits: c_int, tx: *mut Self, tx_cnt: c_int, rx: *mut Self, rx_cnt: c_int) -> BOOL;
+    }
+
+    impl Sealed for u8 {
+        unsafe fn write_read(hdwf: HDWF, dq: c_int, bits: c_int, tx: *mut Self, tx_cnt: c_int, rx: *mut Self, rx_cnt: c_int) -> BOOL {
+            FDwfDigitalSpiWriteRead(hdwf, dq, bits, tx, tx_cnt, rx, rx_cnt)
+        }
+    }
+
+    impl Sealed for u16 {
+        unsafe fn write_read(hdwf: HDWF, dq: c_int, bits: c_int, tx: *mut Self, tx_cnt: c_int, rx: *mut Self, rx_cnt: c_int) -> BOOL {
+            FDwfDigitalSpiWriteRead16(hdwf, dq, bits, tx, tx_cnt, rx, rx_cnt)
+        }
+    }
+
+    impl Sealed for u32 {
+        unsafe fn write_read(hdwf: HDWF, dq: c_int, bits: c_int, tx: *mut Self, tx_cnt: c_int, rx: *mut Self, rx_cnt: c_int) -> BOOL {
+            FDwfDigitalSpiWriteRead32(hdwf, dq, bits, tx, tx_cnt, rx, rx_cnt)
+        }
+    }
+}
+
+/// Word type which can be transferred over SPI
+pub trait SpiWord: Copy + spi_word::Sealed {
+    const BITS: u32;
+}
+
+impl SpiWord for u8 {
+    const BITS: u32 = 8;
+}
+
+impl SpiWord for u16 {
+    const BITS: u32 = 16;
+}
+
+impl SpiWord for u32 {
+    const BITS: u32 = 32;
+}
+
+pub struct Spi<'a> {
+    device: &'a Device,
+}
+
+impl<'a> Spi<'a> {
+    fn configure(&self, config: &SpiConfig) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalSpiReset(self.device.handle))?;
+            handle_dwf_errors(FDwfDigitalSpiFrequencySet(self.device.handle, config.frequency))?;
+            handle_dwf_errors(FDwfDigitalSpiClockSet(self.device.handle, config.clock as c_int))?;
+            for (dq, pin) in config.dq.iter().enumerate() {
+                if let Some(pin) = pin {
+                    handle_dwf_errors(FDwfDigitalSpiDataSet(self.device.handle, dq as c_int, *pin as c_int))?;
+                }
+            }
+            handle_dwf_errors(FDwfDigitalSpiModeSet(self.device.handle, config.mode.code()))?;
+            handle_dwf_errors(FDwfDigitalSpiOrderSet(self.device.handle, config.bit_order.code()))?;
+        }
+        Ok(())
+    }
+
+    /// Drives the chip select line `pin`
+    pub fn select(&self, pin: u32, level: ChipSelect) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalSpiSelect(self.device.handle, pin as c_int, level.code()))?;
+        }
+        Ok(())
+    }
+
+    /// Writes `tx` and then reads `rx.len()` words, using all the bits of a word
+    pub fn transfer<W: SpiWord>(&self, width: SpiBusWidth, tx: &[W], rx: &mut [W]) -> Result<()> {
+        self.transfer_bits(width, W::BITS, tx, rx)
+    }
+
+    /// Writes `tx` and then reads `rx.len()` words of `bits_per_word` bits each
+    pub fn transfer_bits<W: SpiWord>(&self, width: SpiBusWidth, bits_per_word: u32, tx: &[W], rx: &mut [W]) -> Result<()> {
+        if bits_per_word < 1 || bits_per_word > W::BITS {
+            return Err(Error::new(ErrorKind::InvalidParameter2,
+                                  &format!("{} bits per word don't fit in a {} bit word", bits_per_word, W::BITS)));
+        }
+        unsafe {
+            handle_dwf_errors(W::write_read(self.device.handle, width.code(), bits_per_word as c_int,
+                                            tx.as_ptr() as *mut W, tx.len() as c_int,
+                                            rx.as_mut_ptr(), rx.len() as c_int))?;
+        }
+        Ok(())
+    }
+
+    pub fn write<W: SpiWord>(&self, width: SpiBusWidth, tx: &[W]) -> Result<()> {
+        self.transfer(width, tx, &mut [])
+    }
+
+    pub fn read<W: SpiWord>(&self, width: SpiBusWidth, rx: &mut [W]) -> Result<()> {
+        self.transfer(width, &[], rx)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct I2cConfig {
+    pub rate: f64,
+    /// Digital I/O line used for SCL
+    pub scl: u32,
+    /// Digital I/O line used for SDA
+    pub sda: u32,
+    /// NAK the last byte of a read, as most devices expect
+    pub nak_last_read_byte: bool,
+}
+
+impl Default for I2cConfig {
+    fn default() -> Self {
+        I2cConfig {
+            rate: 100e3,
+            scl: 0,
+            sda: 1,
+            nak_last_read_byte: true,
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum I2cError {
+    Dwf(Error),
+    /// `byte_ix` counts bytes on the bus starting from the address byte, which is #0
+    Nak { byte_ix: usize },
+}
+
+impl Display for I2cError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            I2cError::Dwf(e) => e.fmt(f),
+            I2cError::Nak { byte_ix: 0 } => f.write_str("Address was not acknowledged"),
+            I2cError::Nak { byte_ix } => write!(f, "Byte #{} was not acknowledged", byte_ix),
+        }
+    }
+}
+
+impl From<Error> for I2cError {
+    fn from(e: Error) -> Self {
+        I2cError::Dwf(e)
+    }
+}
+
+fn handle_nak(nak: c_int) -> std::result::Result<(), I2cError> {
+    if nak != 0 {
+        Err(I2cError::Nak { byte_ix: nak as usize - 1 })
+    } else {
+        Ok(())
+    }
+}
+
+pub struct I2c<'a> {
+    device: &'a Device,
+}
+
+impl<'a> I2c<'a> {
+    fn configure(&self, config: &I2cConfig) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalI2cReset(self.device.handle))?;
+            handle_dwf_errors(FDwfDigitalI2cRateSet(self.device.handle, config.rate))?;
+            handle_dwf_errors(FDwfDigitalI2cSclSet(self.device.handle, config.scl as c_int))?;
+            handle_dwf_errors(FDwfDigitalI2cSdaSet(self.device.handle, config.sda as c_int))?;
+            handle_dwf_errors(FDwfDigitalI2cReadNakSet(self.device.handle, to_c_bool(config.nak_last_read_byte)))?;
+        }
+        Ok(())
+    }
+
+    fn address(address: u8) -> Result<u8> {
+        if address > 0x7f {
+            return Err(Error::new(ErrorKind::InvalidParameter1, &format!("{:#x} is not a 7-bit address", address)));
+        }
+        Ok(address << 1)
+    }
+
+    /// Clocks SCL until the bus is released, returns `true` if it's free afterwards
+    pub fn recover_bus(&self) -> Result<bool> {
+        unsafe {
+            let mut free: c_int = 0;
+            handle_dwf_errors(FDwfDigitalI2cClear(self.device.handle, &mut free as *mut c_int))?;
+            Ok(free != 0)
+        }
+    }
+
+    pub fn write(&self, address: u8, data: &[u8]) -> std::result::Result<(), I2cError> {
+        let address = I2c::address(address)?;
+        let mut nak: c_int = 0;
+        unsafe {
+            handle_dwf_errors(FDwfDigitalI2cWrite(self.device.handle, address, data.as_ptr() as *mut u8, data.len() as c_int,
+                                                  &mut nak as *mut c_int))?;
+        }
+        handle_nak(nak)
+    }
+
+    pub fn write_one(&self, address: u8, byte: u8) -> std::result::Result<(), I2cError> {
+        let address = I2c::address(address)?;
+        let mut nak: c_int = 0;
+        unsafe {
+            handle_dwf_errors(FDwfDigitalI2cWriteOne(self.device.handle, address, byte, &mut nak as *mut c_int))?;
+        }
+        handle_nak(nak)
+    }
+
+    pub fn read(&self, address: u8, buf: &mut [u8]) -> std::result::Result<(), I2cError> {
+        let address = I2c::address(address)?;
+        let mut nak: c_int = 0;
+        unsafe {
+            handle_dwf_errors(FDwfDigitalI2cRead(self.device.handle, address, buf.as_mut_ptr(), buf.len() as c_int,
+                                                 &mut nak as *mut c_int))?;
+        }
+        handle_nak(nak)
+    }
+
+    /// Writes `tx`, then reads into `rx` after a repeated start
+    pub fn write_read(&self, address: u8, tx: &[u8], rx: &mut [u8]) -> std::result::Result<(), I2cError> {
+        let address = I2c::address(address)?;
+        let mut nak: c_int = 0;
+        unsafe {
+            handle_dwf_errors(FDwfDigitalI2cWriteRead(self.device.handle, address,
+                                                      tx.as_ptr() as *mut u8, tx.len() as c_int,
+                                                      rx.as_mut_ptr(), rx.len() as c_int,
+                                                      &mut nak as *mut c_int))?;
+        }
+        handle_nak(nak)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CanConfig {
+    pub rate: f64,
+    /// `true` if the recessive level is high
+    pub high_polarity: bool,
+    /// Digital I/O line used for transmission
+    pub tx: u32,
+    /// Digital I/O line used for reception
+    pub rx: u32,
+}
+
+impl Default for CanConfig {
+    fn default() -> Self {
+        CanConfig {
+            rate: 1e6,
+            high_polarity: true,
+            tx: 0,
+            rx: 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CanId {
+    /// 11-bit identifier
+    Standard(u16),
+    /// 29-bit identifier
+    Extended(u32),
+}
+
+impl CanId {
+    fn validate(self) -> Result<()> {
+        match self {
+            CanId::Standard(id) if id > 0x7ff =>
+                Err(Error::new(ErrorKind::InvalidParameter1, &format!("{:#x} is not an 11-bit CAN identifier", id))),
+            CanId::Extended(id) if id > 0x1fff_ffff =>
+                Err(Error::new(ErrorKind::InvalidParameter1, &format!("{:#x} is not a 29-bit CAN identifier", id))),
+            _ => Ok(()),
+        }
+    }
+
+    fn raw(self) -> (c_int, bool) {
+        match self {
+            CanId::Standard(id) => (id as c_int, false),
+            CanId::Extended(id) => (id as c_int, true),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CanFrame {
+    id: CanId,
+    remote: bool,
+    dlc: u8,
+    data: [u8; 8],
+}
+
+impl CanFrame {
+    pub fn new(id: CanId, data: &[u8]) -> Result<CanFrame> {
+        id.validate()?;
+        if data.len() > 8 {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("CAN payload is {} bytes long, 8 at most", data.len())));
+        }
+        let mut frame = CanFrame {
+            id,
+            remote: false,
+            dlc: data.len() as u8,
+            data: [0; 8],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Ok(frame)
+    }
+
+    /// Remote transmission request for `dlc` bytes
+    pub fn remote(id: CanId, dlc: u8) -> Result<CanFrame> {
+        id.validate()?;
+        if dlc > 8 {
+            return Err(Error::new(ErrorKind::InvalidParameter2, &format!("DLC is {}, 8 at most", dlc)));
+        }
+        Ok(CanFrame {
+            id,
+            remote: true,
+            dlc,
+            data: [0; 8],
+        })
+    }
+
+    pub fn id(&self) -> CanId {
+        self.id
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    pub fn dlc(&self) -> u8 {
+        self.dlc
+    }
+
+    /// Payload, always empty for remote frames
+    pub fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            &self.data[..self.dlc as usize]
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CanRxStatus {
+    NoFrame,
+    Received(CanFrame),
+    BitStuffingError,
+    CrcError,
+    Error(i32),
+}
+
+pub struct Can<'a> {
+    device: &'a Device,
+}
+
+impl<'a> Can<'a> {
+    fn configure(&self, config: &CanConfig) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDigitalCanReset(self.device.handle))?;
+            handle_dwf_errors(FDwfDigitalCanRateSet(self.device.handle, config.rate))?;
+            handle_dwf_errors(FDwfDigitalCanPolaritySet(self.device.handle, to_c_bool(config.high_polarity)))?;
+            handle_dwf_errors(FDwfDigitalCanTxSet(self.device.handle, config.tx as c_int))?;
+            handle_dwf_errors(FDwfDigitalCanRxSet(self.device.handle, config.rx as c_int))?;
+
+            // ID -1 starts driving the idle level, a zero-length receive starts the reception
+            handle_dwf_errors(FDwfDigitalCanTx(self.device.handle, -1, 0, 0, 0, std::ptr::null_mut()))?;
+        }
+        self.receive()?;
+        Ok(())
+    }
+
+    pub fn transmit(&self, frame: &CanFrame) -> Result<()> {
+        let (id, extended) = frame.id.raw();
+        let mut data = frame.data;
+        unsafe {
+            handle_dwf_errors(FDwfDigitalCanTx(self.device.handle, id, to_c_bool(extended), to_c_bool(frame.remote),
+                                               frame.dlc as c_int, data.as_mut_ptr()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the next received frame, if there's one, without blocking
+    pub fn receive(&self) -> Result<CanRxStatus> {
+        let (mut id, mut extended, mut remote, mut dlc, mut status): (c_int, c_int, c_int, c_int, c_int) = (0, 0, 0, 0, 0);
+        let mut data = [0u8; 8];
+        unsafe {
+            handle_dwf_errors(FDwfDigitalCanRx(self.device.handle,
+                                               &mut id as *mut c_int,
+                                               &mut extended as *mut c_int,
+                                               &mut remote as *mut c_int,
+                                               &mut dlc as *mut c_int,
+                                               data.as_mut_ptr(), data.len() as c_int,
+                                               &mut status as *mut c_int))?;
+        }
+        Ok(match status {
+            0 => CanRxStatus::NoFrame,
+            1 => CanRxStatus::Received(CanFrame {
+                id: if extended != 0 { CanId::Extended(id as u32) } else { CanId::Standard(id as u16) },
+                remote: remote != 0,
+                dlc: dlc.max(0).min(8) as u8,
+                data,
+            }),
+            2 => CanRxStatus::BitStuffingError,
+            3 => CanRxStatus::CrcError,
+            status => CanRxStatus::Error(status),
+        })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[repr(isize)]
+pub enum ImpedanceMode {
+    /// W1-C1-DUT-C2-R-GND
+    DutThenReference = 0,
+    /// W1-C1-R-C2-DUT-GND
+    ReferenceThenDut = 1,
+    /// Impedance Analyzer adapter for Analog Discovery
+    Adapter = 8,
+}
+
+impl ImpedanceMode {
+    fn code(self) -> c_int {
+        self as c_int
+    }
+
+    fn from_code(code: c_int) -> Result<ImpedanceMode> {
+        match code {
+            0 => Ok(ImpedanceMode::DutThenReference),
+            1 => Ok(ImpedanceMode::ReferenceThenDut),
+            8 => Ok(ImpedanceMode::Adapter),
+            _ => Err(Error::new(ErrorKind::NotSupported, &format!("unknown impedance mode #{}", code))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ImpedanceCompensation {
+    pub open_resistance: f64,
+    pub open_reactance: f64,
+    pub short_resistance: f64,
+    pub short_reactance: f64,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ImpedanceInput {
+    pub gain: f64,
+    /// Radians
+    pub phase: f64,
+}
+
+/// Phases are in radians, everything else is in SI units
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ImpedanceMeasurement {
+    pub impedance: f64,
+    pub impedance_phase: f64,
+    pub resistance: f64,
+    pub reactance: f64,
+    pub admittance: f64,
+    pub admittance_phase: f64,
+    pub conductance: f64,
+    pub susceptance: f64,
+    pub series_capacitance: f64,
+    pub parallel_capacitance: f64,
+    pub series_inductance: f64,
+    pub parallel_inductance: f64,
+    pub dissipation: f64,
+    pub quality: f64,
+    pub inputs: [ImpedanceInput; 2],
+}
+
+pub struct Impedance<'a> {
+    device: &'a Device,
+}
+
+impl<'a> Impedance<'a> {
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_mode(&self, mode: ImpedanceMode) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceModeSet(self.device.handle, mode.code()))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_mode(&self) -> Result<ImpedanceMode> {
+        unsafe {
+            let mut mode: c_int = 0;
+            handle_dwf_errors(FDwfAnalogImpedanceModeGet(self.device.handle, &mut mode as *mut c_int))?;
+            ImpedanceMode::from_code(mode)
+        }
+    }
+
+    pub fn set_reference(&self, ohms: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceReferenceSet(self.device.handle, ohms))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_reference(&self) -> Result<f64> {
+        unsafe {
+            let mut ohms: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogImpedanceReferenceGet(self.device.handle, &mut ohms as *mut f64))?;
+            Ok(ohms)
+        }
+    }
+
+    pub fn set_frequency(&self, freq: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceFrequencySet(self.device.handle, freq))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_frequency(&self) -> Result<f64> {
+        unsafe {
+            let mut freq: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogImpedanceFrequencyGet(self.device.handle, &mut freq as *mut f64))?;
+            Ok(freq)
+        }
+    }
+
+    pub fn set_amplitude(&self, volts: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceAmplitudeSet(self.device.handle, volts))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_amplitude(&self) -> Result<f64> {
+        unsafe {
+            let mut volts: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogImpedanceAmplitudeGet(self.device.handle, &mut volts as *mut f64))?;
+            Ok(volts)
+        }
+    }
+
+    pub fn set_offset(&self, volts: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceOffsetSet(self.device.handle, volts))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_offset(&self) -> Result<f64> {
+        unsafe {
+            let mut volts: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogImpedanceOffsetGet(self.device.handle, &mut volts as *mut f64))?;
+            Ok(volts)
+        }
+    }
+
+    /// Probe resistance in ohms and capacitance in farads
+    pub fn set_probe(&self, resistance: f64, capacitance: f64) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceProbeSet(self.device.handle, resistance, capacitance))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_probe(&self) -> Result<(f64, f64)> {
+        unsafe {
+            let (mut resistance, mut capacitance): (f64, f64) = (0.0, 0.0);
+            handle_dwf_errors(FDwfAnalogImpedanceProbeGet(self.device.handle, &mut resistance as *mut f64, &mut capacitance as *mut f64))?;
+            Ok((resistance, capacitance))
+        }
+    }
+
+    pub fn set_min_periods(&self, periods: u32) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedancePeriodSet(self.device.handle, periods as c_int))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_min_periods(&self) -> Result<u32> {
+        unsafe {
+            let mut periods: c_int = 0;
+            handle_dwf_errors(FDwfAnalogImpedancePeriodGet(self.device.handle, &mut periods as *mut c_int))?;
+            Ok(periods as u32)
+        }
+    }
+
+    pub fn set_compensation(&self, comp: ImpedanceCompensation) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceCompSet(self.device.handle,
+                                                         comp.open_resistance, comp.open_reactance,
+                                                         comp.short_resistance, comp.short_reactance))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_compensation(&self) -> Result<ImpedanceCompensation> {
+        unsafe {
+            let mut comp = ImpedanceCompensation::default();
+            handle_dwf_errors(FDwfAnalogImpedanceCompGet(self.device.handle,
+                                                         &mut comp.open_resistance as *mut f64,
+                                                         &mut comp.open_reactance as *mut f64,
+                                                         &mut comp.short_resistance as *mut f64,
+                                                         &mut comp.short_reactance as *mut f64))?;
+            Ok(comp)
+        }
+    }
+
+    pub fn reset_compensation(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceCompReset(self.device.handle))?;
+        }
+        Ok(())
+    }
+
+    pub fn start(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceConfigure(self.device.handle, to_c_bool(true)))?;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfAnalogImpedanceConfigure(self.device.handle, to_c_bool(false)))?;
+        }
+        Ok(())
+    }
+
+    pub fn get_status(&self) -> Result<AnalogAcquisitionStatus> {
+        unsafe {
+            let mut state: DwfState = 0;
+            handle_dwf_errors(FDwfAnalogImpedanceStatus(self.device.handle, (&mut state) as *mut DwfState))?;
+            AnalogAcquisitionStatus::from_code(state)
+        }
+    }
+
+    fn status_measure(&self, measure: DwfAnalogImpedance) -> Result<f64> {
+        unsafe {
+            let mut value: f64 = 0.0;
+            handle_dwf_errors(FDwfAnalogImpedanceStatusMeasure(self.device.handle, measure, &mut value as *mut f64))?;
+            Ok(value)
+        }
+    }
+
+    fn status_input(&self, ix: c_int) -> Result<ImpedanceInput> {
+        unsafe {
+            let mut input = ImpedanceInput::default();
+            handle_dwf_errors(FDwfAnalogImpedanceStatusInput(self.device.handle, ix,
+                                                             &mut input.gain as *mut f64, &mut input.phase as *mut f64))?;
+            Ok(input)
+        }
+    }
+
+    /// Waits for the running measurement to complete and reads all of its results.
+    /// Fails if the analyzer isn't started.
+    pub fn measure(&self) -> Result<ImpedanceMeasurement> {
+        loop {
+            match self.get_status()? {
+                AnalogAcquisitionStatus::Done => break,
+                AnalogAcquisitionStatus::Ready | AnalogAcquisitionStatus::Config =>
+                    return Err(Error::new(ErrorKind::NotSupported, "the impedance analyzer isn't running")),
+                _ => std::thread::sleep(std::time::Duration::from_millis(1)),
+            }
+        }
+        Ok(ImpedanceMeasurement {
+            impedance: self.status_measure(DwfAnalogImpedanceImpedance)?,
+            impedance_phase: self.status_measure(DwfAnalogImpedanceImpedancePhase)?,
+            resistance: self.status_measure(DwfAnalogImpedanceResistance)?,
+            reactance: self.status_measure(DwfAnalogImpedanceReactance)?,
+            admittance: self.status_measure(DwfAnalogImpedanceAdmittance)?,
+            admittance_phase: self.status_measure(DwfAnalogImpedanceAdmittancePhase)?,
+            conductance: self.status_measure(DwfAnalogImpedanceConductance)?,
+            susceptance: self.status_measure(DwfAnalogImpedanceSusceptance)?,
+            series_capacitance: self.status_measure(DwfAnalogImpedanceSeriesCapactance)?,
+            parallel_capacitance: self.status_measure(DwfAnalogImpedanceParallelCapacitance)?,
+            series_inductance: self.status_measure(DwfAnalogImpedanceSeriesInductance)?,
+            parallel_inductance: self.status_measure(DwfAnalogImpedanceParallelInductance)?,
+            dissipation: self.status_measure(DwfAnalogImpedanceDissipation)?,
+            quality: self.status_measure(DwfAnalogImpedanceQuality)?,
+            inputs: [self.status_input(0)?, self.status_input(1)?],
+        })
+    }
+}
+
+
+pub struct Device {
+    handle: HDWF,
+    id: DEVID,
+}
+
+impl Device {
+    pub fn set_auto_configure(&self, enabled: bool) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDeviceAutoConfigureSet(self.handle, to_c_bool(enabled)))?;
+        }
+        Ok(())
+    }
+
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            handle_dwf_errors(FDwfDeviceReset(self.handle))?;
         }
         Ok(())
     }
@@ -645,17 +4319,108 @@ impl Device {
         }
     }
 
+    pub fn analog_out_count(&self) -> Result<u32> {
+        unsafe {
+            let mut cnt: c_int = 0;
+            handle_dwf_errors(FDwfAnalogOutCount(self.handle, &mut cnt as *mut c_int))?;
+            Ok(cnt as u32)
+        }
+    }
+
+    /// Links `followers` to the `master` channel, see `AnalogOutGroup`
+    pub fn analog_out_group(&self, master: u32, followers: &[u32]) -> Result<AnalogOutGroup> {
+        let cnt = self.analog_out_count()?;
+        if let Some(ix) = std::iter::once(&master).chain(followers).find(|&&ix| ix >= cnt) {
+            return Err(Error::new(ErrorKind::InvalidParameter1, &format!("no AnalogOut channel #{}, the device has {}", ix, cnt)));
+        }
+        let group = AnalogOutGroup {
+            channels: std::iter::once(&master).chain(followers.iter().filter(|&&ix| ix != master))
+                .map(|&ix| self.analog_out(ix))
+                .collect(),
+        };
+        for channel in group.channels() {
+            channel.set_master(group.master())?;
+        }
+        Ok(group)
+    }
+
     pub fn analog_io(&self) -> AnalogIO {
         AnalogIO {
             device: &self,
         }
     }
 
+    pub fn power_supplies(&self) -> Result<PowerSupplies> {
+        PowerSupplies::new(self.analog_io())
+    }
+
     pub fn analog_input(&self) -> AnalogIn {
         AnalogIn {
             device: &self,
         }
     }
+
+    pub fn impedance(&self) -> Impedance {
+        Impedance {
+            device: &self,
+        }
+    }
+
+    pub fn digital_io(&self) -> Result<DigitalIO> {
+        let mut io = DigitalIO {
+            device: &self,
+            wide: false,
+        };
+        let caps = io.capabilities()?;
+        io.wide = caps.line_count() > 32;
+        Ok(io)
+    }
+
+    pub fn digital_input(&self) -> DigitalIn {
+        DigitalIn {
+            device: &self,
+        }
+    }
+
+    pub fn digital_out(&self) -> DigitalOut {
+        DigitalOut {
+            device: &self,
+        }
+    }
+
+    pub fn uart(&self, config: &UartConfig) -> Result<Uart> {
+        let uart = Uart {
+            device: &self,
+            read_timeout: None,
+            pending_error: None,
+        };
+        uart.configure(config)?;
+        Ok(uart)
+    }
+
+    pub fn spi(&self, config: &SpiConfig) -> Result<Spi> {
+        let spi = Spi {
+            device: &self,
+        };
+        spi.configure(config)?;
+        Ok(spi)
+    }
+
+    pub fn i2c(&self, config: &I2cConfig) -> Result<I2c> {
+        let i2c = I2c {
+            device: &self,
+        };
+        i2c.configure(config)?;
+        Ok(i2c)
+    }
+
+    pub fn can(&self, config: &CanConfig) -> Result<Can> {
+        let can = Can {
+            device: &self,
+        };
+        can.configure(config)?;
+        Ok(can)
+    }
 }
 
 impl Drop for Device {
@@ -665,3 +4430,105 @@ impl Drop for Device {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_spans_wrap_around() {
+        assert_eq!(ring_spans(2, 3, 8), [(2, 3), (0, 0)]);
+        assert_eq!(ring_spans(0, 8, 8), [(0, 8), (0, 0)]);
+        assert_eq!(ring_spans(6, 4, 8), [(6, 2), (0, 2)]);
+        assert_eq!(ring_spans(7, 8, 8), [(7, 1), (0, 7)]);
+    }
+
+    #[test]
+    fn noise_envelope_with_offset() {
+        let envelope = NoiseEnvelope {
+            min: vec![-1.0, -2.0, -3.0],
+            max: vec![1.0, 2.0, 3.0],
+            samples_per_point: 4.0,
+            // fetched from data sample 6, the first point covers samples 4..8
+            offset: -2.0,
+        };
+        assert_eq!(envelope.at(0), Some((-1.0, 1.0)));
+        assert_eq!(envelope.at(1), Some((-1.0, 1.0)));
+        assert_eq!(envelope.at(2), Some((-2.0, 2.0)));
+        assert_eq!(envelope.at(9), Some((-3.0, 3.0)));
+        assert_eq!(envelope.at(10), None);
+    }
+
+    #[test]
+    fn scan_cursor_while_filling() {
+        let mut cursor = ScanCursor::new();
+        assert_eq!(cursor.advance(3, 3, 8), (0, 3));
+        assert_eq!(cursor.advance(3, 3, 8), (3, 0));
+        assert_eq!(cursor.advance(5, 5, 8), (3, 2));
+        // filled up and wrapped
+        assert_eq!(cursor.advance(2, 8, 8), (5, 5));
+    }
+
+    #[test]
+    fn scan_cursor_whole_buffer_while_filling() {
+        let mut cursor = ScanCursor::new();
+        assert_eq!(cursor.advance(0, 0, 8), (0, 0));
+        assert_eq!(cursor.advance(0, 8, 8), (0, 8));
+
+        let mut cursor = ScanCursor::new();
+        assert_eq!(cursor.advance(5, 5, 8), (0, 5));
+        assert_eq!(cursor.advance(5, 8, 8), (5, 8));
+    }
+
+    #[test]
+    fn scan_cursor_when_full() {
+        let mut cursor = ScanCursor::new();
+        assert_eq!(cursor.advance(4, 8, 8), (4, 8));
+        assert_eq!(cursor.advance(4, 8, 8), (4, 0));
+        assert_eq!(cursor.advance(1, 8, 8), (4, 5));
+    }
+
+    #[test]
+    fn scan_cursor_restart() {
+        let mut cursor = ScanCursor::new();
+        assert_eq!(cursor.advance(6, 8, 8), (6, 8));
+        assert_eq!(cursor.advance(2, 2, 8), (0, 2));
+
+        cursor.reset();
+        assert_eq!(cursor.advance(6, 8, 8), (6, 8));
+    }
+
+    #[test]
+    fn can_id_bounds() {
+        assert!(CanFrame::new(CanId::Standard(0x7ff), &[]).is_ok());
+        assert_eq!(CanFrame::new(CanId::Standard(0x800), &[]).unwrap_err().kind(), &ErrorKind::InvalidParameter1);
+        assert!(CanFrame::new(CanId::Extended(0x1fff_ffff), &[]).is_ok());
+        assert_eq!(CanFrame::new(CanId::Extended(0x2000_0000), &[]).unwrap_err().kind(), &ErrorKind::InvalidParameter1);
+    }
+
+    #[test]
+    fn can_frame_length() {
+        let frame = CanFrame::new(CanId::Standard(0x123), &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(frame.dlc(), 8);
+        assert_eq!(frame.data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(CanFrame::new(CanId::Standard(0x123), &[0; 9]).unwrap_err().kind(), &ErrorKind::InvalidParameter2);
+        assert_eq!(CanFrame::remote(CanId::Standard(0x123), 9).unwrap_err().kind(), &ErrorKind::InvalidParameter2);
+    }
+
+    #[test]
+    fn can_remote_frame_has_no_data() {
+        let frame = CanFrame::remote(CanId::Extended(0x1234), 4).unwrap();
+        assert!(frame.is_remote());
+        assert_eq!(frame.dlc(), 4);
+        assert!(frame.data().is_empty());
+    }
+
+    #[test]
+    fn scaling_full_scale() {
+        let scaling = Scaling { range: 10.0, offset: 1.0, adc_bits: 14 };
+        assert_eq!(scaling.to_volts(i16::MIN), 1.0 - 5.0);
+        assert_eq!(scaling.to_volts(0), 1.0);
+        assert_eq!(scaling.to_volts(i16::MAX), 1.0 + 5.0 - 10.0 / 65536.0);
+        assert_eq!(scaling.resolution(), 10.0 / 16384.0);
+    }
+}